@@ -0,0 +1,369 @@
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpStream, UdpSocket},
+    sync::oneshot,
+    task::JoinHandle,
+};
+
+use crate::{client::is_server_failure, dns_error::DnsError, ClientConfig, Message, Protocol};
+
+/// Sends messages from async code, like [`crate::Client`] does from
+/// blocking code.
+///
+/// All queries share one UDP socket per address family, however many are in
+/// flight.  A task reads the replies and hands each to the query with its ID
+/// and question that was sent to the server it came from, anything else is
+/// dropped.  A query whose ID is already in flight is sent with a fresh one,
+/// the reply carries the ID of the query again.
+///
+/// Timeouts, retransmission, failover, rotation and EDNS(0) follow the
+/// [`ClientConfig`], truncated replies are repeated over TCP.  Only
+/// [`Protocol::Udp`] and [`Protocol::Tcp`] are supported.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::sync::Arc;
+/// use dns::{AsyncClient, ClientConfig, Message};
+///
+/// # async fn run() {
+/// let client = Arc::new(AsyncClient::new(ClientConfig::new()).await.unwrap());
+///
+/// let mut tasks = vec![];
+/// for name in ["example.com", "example.net", "example.org"] {
+///     let client = client.clone();
+///     tasks.push(tokio::spawn(async move {
+///         let mut message = Message::new();
+///         message.set_questions(vec![name.to_string()]).unwrap();
+///         client.send(&message).await
+///     }));
+/// }
+/// for task in tasks {
+///     println!("{:?}", task.await.unwrap().unwrap().answer);
+/// }
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct AsyncClient {
+    config: ClientConfig,
+    /// the server to start with for the next query when rotating
+    next: AtomicUsize,
+    v4: Option<Socket>,
+    v6: Option<Socket>,
+}
+
+/// A shared UDP socket and the queries waiting for a reply on it.
+#[derive(Debug)]
+struct Socket {
+    socket: Arc<UdpSocket>,
+    pending: Arc<Mutex<HashMap<u16, Pending>>>,
+    tokens: AtomicU64,
+    receiver: JoinHandle<()>,
+}
+
+#[derive(Debug)]
+struct Pending {
+    /// tells this query apart from a later one with the same ID
+    token: u64,
+    query: Message,
+    server: SocketAddr,
+    reply: oneshot::Sender<Message>,
+}
+
+/// Removes a query from the pending ones when it is done or given up.
+struct Registration<'a> {
+    pending: &'a Mutex<HashMap<u16, Pending>>,
+    id: u16,
+    token: u64,
+}
+impl Drop for Registration<'_> {
+    fn drop(&mut self) {
+        let mut pending = self.pending.lock().unwrap();
+        if pending.get(&self.id).is_some_and(|p| p.token == self.token) {
+            pending.remove(&self.id);
+        }
+    }
+}
+
+impl AsyncClient {
+    /// # Creates a new AsyncClient
+    ///
+    /// # Note
+    ///
+    /// Binds the sockets for the address families of the servers, which
+    /// needs a running tokio runtime.
+    pub async fn new(config: ClientConfig) -> Result<AsyncClient, DnsError> {
+        let servers = config.servers();
+        let v4 = match servers.iter().any(SocketAddr::is_ipv4) {
+            true => Some(Socket::bind(IpAddr::V4(Ipv4Addr::UNSPECIFIED)).await?),
+            false => None,
+        };
+        let v6 = match servers.iter().any(SocketAddr::is_ipv6) {
+            true => Some(Socket::bind(IpAddr::V6(Ipv6Addr::UNSPECIFIED)).await?),
+            false => None,
+        };
+        Ok(AsyncClient {
+            config,
+            next: AtomicUsize::new(0),
+            v4,
+            v6,
+        })
+    }
+
+    /// # Returns the settings of this client
+    pub fn config(&self) -> &ClientConfig {
+        &self.config
+    }
+
+    /// # Sends the message
+    ///
+    /// # Returns
+    ///
+    /// returns the response of the first server that answered, see
+    /// [`crate::Client::send()`].
+    pub async fn send(&self, message: &Message) -> Result<Message, DnsError> {
+        let message = self.config.prepare(message)?;
+        let servers = self.config.server_order(&self.next);
+        if servers.is_empty() {
+            return Err(DnsError::NoServers);
+        }
+        let attempts = self.config.attempts().max(1);
+        let mut last = None;
+        // the servers that answered with a failure and their last answer
+        let mut failed = vec![];
+        let mut failure = None;
+        for round in 0..attempts {
+            let timeout = self.config.round_timeout(round);
+            for server in servers.iter().filter(|server| !failed.contains(*server)).copied().collect::<Vec<_>>() {
+                match self.exchange(&message, server, timeout).await {
+                    Ok(response) if is_server_failure(&response) => {
+                        failed.push(server);
+                        failure = Some(response);
+                    }
+                    Ok(response) => return Ok(response),
+                    Err(DnsError::Timeout { .. }) => {}
+                    Err(e) => last = Some(e),
+                }
+            }
+        }
+        match failure {
+            Some(response) => Ok(response),
+            None => Err(last.unwrap_or(DnsError::RetriesExhausted { attempts })),
+        }
+    }
+
+    async fn exchange(&self, query: &Message, server: SocketAddr, timeout: Duration) -> Result<Message, DnsError> {
+        match self.config.protocol() {
+            Protocol::Udp => {
+                let response = self.send_udp(query, server, timeout).await?;
+                if response.header.tc() {
+                    return send_tcp(query, server, timeout).await;
+                }
+                Ok(response)
+            }
+            Protocol::Tcp => send_tcp(query, server, timeout).await,
+            #[allow(unreachable_patterns)]
+            protocol => Err(DnsError::UnsupportedProtocol(protocol)),
+        }
+    }
+
+    async fn send_udp(&self, query: &Message, server: SocketAddr, timeout: Duration) -> Result<Message, DnsError> {
+        let socket = match server {
+            SocketAddr::V4(_) => self.v4.as_ref(),
+            SocketAddr::V6(_) => self.v6.as_ref(),
+        };
+        let socket = socket.ok_or(DnsError::NoServers)?;
+        let (registration, data, reply) = socket.register(query, server)?;
+        socket.socket.send_to(&data, server).await.map_err(DnsError::UdpSocketError)?;
+        let response = match tokio::time::timeout(timeout, reply).await {
+            Ok(Ok(mut response)) => {
+                response.header.set_id(query.header.id);
+                response
+            }
+            // the receiving task ended
+            Ok(Err(_)) => return Err(DnsError::ConnectionClosed),
+            Err(_) => return Err(DnsError::Timeout { server, timeout }),
+        };
+        drop(registration);
+        Ok(response)
+    }
+}
+impl Drop for AsyncClient {
+    fn drop(&mut self) {
+        for socket in [&self.v4, &self.v6].into_iter().flatten() {
+            socket.receiver.abort();
+        }
+    }
+}
+
+impl Socket {
+    async fn bind(ip: IpAddr) -> Result<Socket, DnsError> {
+        let socket = Arc::new(UdpSocket::bind(SocketAddr::new(ip, 0)).await.map_err(DnsError::UdpSocketError)?);
+        let pending = Arc::new(Mutex::new(HashMap::new()));
+        let receiver = tokio::spawn(receive(socket.clone(), pending.clone()));
+        Ok(Socket {
+            socket,
+            pending,
+            tokens: AtomicU64::new(0),
+            receiver,
+        })
+    }
+
+    /// Registers the query under an unused ID, returning it in wire format.
+    #[allow(clippy::type_complexity)]
+    fn register(
+        &self,
+        query: &Message,
+        server: SocketAddr,
+    ) -> Result<(Registration<'_>, Vec<u8>, oneshot::Receiver<Message>), DnsError> {
+        let mut query = query.clone();
+        let (reply, receiver) = oneshot::channel();
+        let token = self.tokens.fetch_add(1, Ordering::Relaxed);
+        let mut pending = self.pending.lock().unwrap();
+        if pending.len() > u16::MAX as usize {
+            return Err(DnsError::TooManyPending);
+        }
+        while pending.contains_key(&query.header.id) {
+            query.header.set_id(rand::random());
+        }
+        let data = query.encode()?;
+        let id = query.header.id;
+        pending.insert(
+            id,
+            Pending {
+                token,
+                query,
+                server,
+                reply,
+            },
+        );
+        let registration = Registration {
+            pending: &self.pending,
+            id,
+            token,
+        };
+        Ok((registration, data, receiver))
+    }
+}
+
+/// Hands the replies on the socket to the waiting queries.
+async fn receive(socket: Arc<UdpSocket>, pending: Arc<Mutex<HashMap<u16, Pending>>>) {
+    let mut buf = vec![0; u16::MAX as usize];
+    loop {
+        // errors, like ICMP port unreachable on some platforms, concern a
+        // single query which times out on its own
+        let Ok((amt, from)) = socket.recv_from(&mut buf).await else { continue };
+        let Ok(response) = Message::parse(&buf[..amt]) else { continue };
+        let mut pending = pending.lock().unwrap();
+        let id = response.header.id;
+        if pending.get(&id).is_some_and(|p| p.server == from && response.is_response_to(&p.query)) {
+            let waiting = pending.remove(&id).unwrap();
+            let _ = waiting.reply.send(response);
+        }
+    }
+}
+
+/// Sends the query over a new TCP connection.
+async fn send_tcp(query: &Message, server: SocketAddr, timeout: Duration) -> Result<Message, DnsError> {
+    let data = query.encode()?;
+    let len = u16::try_from(data.len()).map_err(|_| DnsError::MessageTooLong(data.len()))?;
+    let exchange = async {
+        let mut stream = TcpStream::connect(server).await?;
+        stream.set_nodelay(true)?;
+        let mut frame = len.to_be_bytes().to_vec();
+        frame.extend_from_slice(&data);
+        stream.write_all(&frame).await?;
+        loop {
+            let len = stream.read_u16().await?;
+            let mut buf = vec![0; len as usize];
+            stream.read_exact(&mut buf).await?;
+            if let Ok(response) = Message::parse(&buf) {
+                if response.is_response_to(query) {
+                    return Ok(response);
+                }
+            }
+        }
+    };
+    match tokio::time::timeout(timeout, exchange).await {
+        Ok(result) => result.map_err(DnsError::TcpSocketError),
+        Err(_) => Err(DnsError::Timeout { server, timeout }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    /// Collects `count` queries, then answers them in reverse order.
+    async fn batch_server(count: usize) -> (SocketAddr, JoinHandle<HashSet<SocketAddr>>) {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        let handle = tokio::spawn(async move {
+            let mut buf = [0; 512];
+            let mut queries = vec![];
+            for _ in 0..count {
+                let (amt, from) = socket.recv_from(&mut buf).await.unwrap();
+                queries.push((Message::parse(&buf[..amt]).unwrap(), from));
+            }
+            for (query, from) in queries.iter().rev() {
+                let mut response = query.clone();
+                response.header.set_qr(true);
+                socket.send_to(&response.encode().unwrap(), from).await.unwrap();
+            }
+            queries.into_iter().map(|(_, from)| from).collect()
+        });
+        (addr, handle)
+    }
+
+    #[tokio::test]
+    async fn async_client_demultiplexes_concurrent_queries() {
+        let (addr, server) = batch_server(200).await;
+        let mut config = ClientConfig::new();
+        config.set_servers(vec![addr]);
+        let client = Arc::new(AsyncClient::new(config).await.unwrap());
+
+        let mut tasks = vec![];
+        for i in 0..200 {
+            let client = client.clone();
+            tasks.push(tokio::spawn(async move {
+                let mut message = Message::new();
+                message.set_questions(vec![format!("host{i}.example")]).unwrap();
+                // many queries share an ID
+                message.header.set_id(i % 10);
+                let response = client.send(&message).await.unwrap();
+                assert_eq!(response.header.id, message.header.id);
+                assert_eq!(response.question, message.question);
+            }));
+        }
+        for task in tasks {
+            task.await.unwrap();
+        }
+        // all queries came from one socket
+        assert_eq!(server.await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn async_client_times_out() {
+        let silent = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let mut config = ClientConfig::new();
+        config.set_servers(vec![silent.local_addr().unwrap()]);
+        config.set_timeout(Duration::from_millis(20));
+        config.set_attempts(2);
+        let client = AsyncClient::new(config).await.unwrap();
+
+        let result = client.send(&Message::new()).await;
+        assert!(matches!(result, Err(DnsError::RetriesExhausted { attempts: 2 })));
+        assert!(client.v4.as_ref().unwrap().pending.lock().unwrap().is_empty());
+    }
+}
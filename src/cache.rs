@@ -0,0 +1,588 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{DnsClass, Message, Name, Opcode, RData, Rcode, RecordType, Resource, MAX_CHAIN_LEN};
+
+/// What an entry of the cache is about.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Key {
+    /// the records of a name, type and class, or their absence
+    RRset(Name, RecordType, DnsClass),
+    /// the absence of a name in a class
+    Name(Name, DnsClass),
+}
+
+/// A negative answer kept by a [`Cache`] (RFC 2308).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Negative {
+    /// The name does not exist, with the SOA record of its zone.
+    NxDomain(Resource),
+    /// The name has no records of the type, with the SOA record of its zone.
+    NoData(Resource),
+}
+impl Negative {
+    /// # Returns the SOA record of the negative answer
+    pub fn soa(&self) -> &Resource {
+        match self {
+            Negative::NxDomain(soa) | Negative::NoData(soa) => soa,
+        }
+    }
+}
+
+/// Returns true for a standard query, or its response, that neither asks
+/// for DNSSEC records (DO) nor turns off their validation (CD).  The cache
+/// keeps no RRSIG records, and data that was not validated must not reach
+/// queries that expect it to be.
+fn is_plain_query(message: &Message) -> bool {
+    let dnssec_ok = matches!(message.edns(), Ok(Some(edns)) if edns.dnssec_ok);
+    message.header.opcode() == Opcode::Query && !message.header.cd() && !dnssec_ok
+}
+
+/// Sets the counts in the header of a response from the cache, which starts
+/// out as the header of the query, to the lengths of its sections.
+fn counted(mut response: Message) -> Message {
+    response.header.qdcount = response.question.len() as u16;
+    response.header.ancount = response.answer.len() as u16;
+    response.header.nscount = response.authority.len() as u16;
+    response.header.arcount = response.additional.len() as u16;
+    response
+}
+
+/// Keeps RRsets and negative answers until their TTL runs out.
+///
+/// The records of one name, type and class are stored together with the
+/// lowest TTL among them, held between the minimum and maximum TTL of the
+/// cache.  They are handed out with the TTL that is left, RRsets whose TTL
+/// comes out as zero are not stored.  When the cache holds `capacity`
+/// entries, the one used least recently makes room for a new one.
+///
+/// Negative answers with the SOA record of the zone in the authority section
+/// are kept as well (RFC 2308), NXDOMAIN for all types of the name and
+/// NODATA for the type asked for.  They are kept for the lower of the TTL
+/// and the MINIMUM field of the SOA record, at most for the maximum negative
+/// TTL of the cache, and handed out with the SOA record.
+///
+/// A Cache is shared between threads behind an [`std::sync::Arc`], see
+/// [`crate::Client::set_cache()`].
+///
+/// # Example
+///
+/// ```
+/// use dns::{Cache, DnsClass, RData, RecordType, Resource};
+///
+/// let cache = Cache::new(1000);
+/// let name = "example.com".parse().unwrap();
+/// let record = Resource::new("example.com".parse().unwrap(), 300, RData::A([192, 0, 2, 1].into())).unwrap();
+/// cache.insert(vec![record]);
+///
+/// let records = cache.get(&name, RecordType::A, DnsClass::IN).unwrap();
+/// assert!(records[0].ttl <= 300);
+/// assert!(cache.get(&name, RecordType::AAAA, DnsClass::IN).is_none());
+/// ```
+#[derive(Debug)]
+pub struct Cache {
+    capacity: usize,
+    min_ttl: u32,
+    max_ttl: u32,
+    max_negative_ttl: u32,
+    entries: Mutex<Entries>,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    entries: HashMap<Key, Entry>,
+    /// the keys by the time they were last used, oldest first
+    recent: BTreeMap<u64, Key>,
+    clock: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    data: Cached,
+    expires: Instant,
+    used: u64,
+}
+
+#[derive(Debug, Clone)]
+enum Cached {
+    Records(Vec<Resource>),
+    Negative(Negative),
+}
+
+impl Cache {
+    /// # Creates a new Cache for up to `capacity` entries
+    ///
+    /// # Note
+    ///
+    /// This method creates a new Cache with the following default values:
+    ///
+    /// - min_ttl: 0
+    /// - max_ttl: 86400            // one day
+    /// - max_negative_ttl: 10800   // three hours, as RFC 2308 suggests
+    pub fn new(capacity: usize) -> Cache {
+        Cache {
+            capacity,
+            min_ttl: 0,
+            max_ttl: 86400,
+            max_negative_ttl: 10800,
+            entries: Mutex::new(Entries::default()),
+        }
+    }
+
+    /// # Returns how many entries the cache holds at most
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// # Sets the lowest TTL in seconds an entry is kept for
+    pub fn set_min_ttl(&mut self, min_ttl: u32) {
+        self.min_ttl = min_ttl;
+    }
+
+    /// # Returns the lowest TTL in seconds an entry is kept for
+    pub fn min_ttl(&self) -> u32 {
+        self.min_ttl
+    }
+
+    /// # Sets the highest TTL in seconds an RRset is kept for
+    pub fn set_max_ttl(&mut self, max_ttl: u32) {
+        self.max_ttl = max_ttl;
+    }
+
+    /// # Returns the highest TTL in seconds an RRset is kept for
+    pub fn max_ttl(&self) -> u32 {
+        self.max_ttl
+    }
+
+    /// # Sets the highest TTL in seconds a negative answer is kept for
+    pub fn set_max_negative_ttl(&mut self, max_negative_ttl: u32) {
+        self.max_negative_ttl = max_negative_ttl;
+    }
+
+    /// # Returns the highest TTL in seconds a negative answer is kept for
+    pub fn max_negative_ttl(&self) -> u32 {
+        self.max_negative_ttl
+    }
+
+    /// # Returns the number of entries in the cache, including expired ones
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().entries.len()
+    }
+
+    /// # Returns true if the cache holds no entries
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// # Removes all entries
+    pub fn clear(&self) {
+        *self.entries.lock().unwrap() = Entries::default();
+    }
+
+    /// # Stores an RRset
+    ///
+    /// # Arguments
+    ///
+    /// takes records of the same name, type and class, which replace those
+    /// in the cache.  The name, type and class of the first record are
+    /// used for all.
+    pub fn insert(&self, records: Vec<Resource>) {
+        self.insert_at(records, Instant::now());
+    }
+
+    /// # Returns the RRset of a name, type and class
+    ///
+    /// # Returns
+    ///
+    /// returns the records with the TTL that is left, or None if there are
+    /// none or they expired.
+    pub fn get(&self, name: &Name, rtype: RecordType, class: DnsClass) -> Option<Vec<Resource>> {
+        self.get_at(&Key::RRset(name.clone(), rtype, class), Instant::now())
+    }
+
+    /// # Returns the negative answer for a name, type and class
+    ///
+    /// # Returns
+    ///
+    /// returns the NXDOMAIN of the name or the NODATA of the type, with the
+    /// TTL that is left as the TTL of the SOA record.
+    pub fn get_negative(&self, name: &Name, rtype: RecordType, class: DnsClass) -> Option<Negative> {
+        self.negative_at(name, rtype, class, Instant::now())
+    }
+
+    /// # Stores the answer of a response
+    ///
+    /// Only the records on the alias chain of the question are stored, the
+    /// CNAME records and the RRset at its end (see [`Message::chain()`]), so
+    /// a server can not plant records for names it was not asked for.  If
+    /// the chain ends without records, an NXDOMAIN or NODATA response is
+    /// stored as the negative answer for the end of the chain, if the
+    /// authority section has the SOA record of its zone.  Truncated
+    /// responses, those with other errors and responses to queries other
+    /// than a standard QUERY or with the DO or CD bit set are ignored.
+    pub fn insert_response(&self, response: &Message) {
+        self.insert_response_at(response, Instant::now());
+    }
+
+    /// # Answers a query from the cache
+    ///
+    /// # Returns
+    ///
+    /// returns a response to the first question, with the cached CNAME
+    /// records leading to the records asked for, or to a negative answer
+    /// with its SOA record in the authority section and NXDOMAIN or NOERROR
+    /// as the rcode.  Returns None if any entry of the chain is missing,
+    /// and for queries other than a standard QUERY or with the DO or CD bit
+    /// set, which need records or checks the cache does not have.  The
+    /// response has no EDNS.
+    pub fn answer(&self, query: &Message) -> Option<Message> {
+        self.answer_at(query, Instant::now())
+    }
+}
+impl Cache {
+    fn insert_at(&self, records: Vec<Resource>, now: Instant) {
+        let Some(first) = records.first() else { return };
+        let key = Key::RRset(first.name.clone(), first.rtype, first.rclass);
+        let ttl = records.iter().map(|r| r.ttl).min().unwrap_or(0);
+        self.store(key, Cached::Records(records), ttl.clamp(self.min_ttl, self.max_ttl.max(self.min_ttl)), now);
+    }
+
+    /// Stores a negative answer about `name` for the SOA TTL (RFC 2308 5).
+    fn insert_negative_at(&self, name: &Name, rtype: RecordType, class: DnsClass, negative: Negative, now: Instant) {
+        let soa = negative.soa();
+        let Ok(RData::SOA(data)) = soa.data() else { return };
+        let ttl = soa.ttl.min(data.minimum).clamp(self.min_ttl, self.max_negative_ttl.max(self.min_ttl));
+        let key = match negative {
+            Negative::NxDomain(_) => Key::Name(name.clone(), class),
+            Negative::NoData(_) => Key::RRset(name.clone(), rtype, class),
+        };
+        self.store(key, Cached::Negative(negative), ttl, now);
+    }
+
+    fn store(&self, key: Key, data: Cached, ttl: u32, now: Instant) {
+        if ttl == 0 || self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        let used = entries.touch(&key);
+        let entry = Entry {
+            data,
+            expires: now + Duration::from_secs(ttl as u64),
+            used,
+        };
+        entries.entries.insert(key, entry);
+        while entries.entries.len() > self.capacity {
+            let Some((_, oldest)) = entries.recent.pop_first() else { break };
+            entries.entries.remove(&oldest);
+        }
+    }
+
+    /// Returns the entry of the key with the TTL that is left.
+    fn lookup_at(&self, key: &Key, now: Instant) -> Option<Cached> {
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries.entries.get(key)?;
+        let left = entry.expires.saturating_duration_since(now).as_secs();
+        if left == 0 {
+            let used = entry.used;
+            entries.recent.remove(&used);
+            entries.entries.remove(key);
+            return None;
+        }
+        let mut data = entry.data.clone();
+        match &mut data {
+            Cached::Records(records) => records.iter_mut().for_each(|record| record.ttl = left as u32),
+            Cached::Negative(Negative::NxDomain(soa) | Negative::NoData(soa)) => soa.ttl = left as u32,
+        }
+        let used = entries.touch(key);
+        entries.entries.get_mut(key).expect("the entry was found").used = used;
+        Some(data)
+    }
+
+    fn get_at(&self, key: &Key, now: Instant) -> Option<Vec<Resource>> {
+        match self.lookup_at(key, now)? {
+            Cached::Records(records) => Some(records),
+            Cached::Negative(_) => None,
+        }
+    }
+
+    fn negative_at(&self, name: &Name, rtype: RecordType, class: DnsClass, now: Instant) -> Option<Negative> {
+        if let Some(Cached::Negative(negative)) = self.lookup_at(&Key::Name(name.clone(), class), now) {
+            return Some(negative);
+        }
+        match self.lookup_at(&Key::RRset(name.clone(), rtype, class), now)? {
+            Cached::Negative(negative) => Some(negative),
+            Cached::Records(_) => None,
+        }
+    }
+
+    fn insert_response_at(&self, response: &Message, now: Instant) {
+        let rcode = response.rcode();
+        if response.header.tc() || !matches!(rcode, Rcode::NoError | Rcode::NXDomain) || !is_plain_query(response) {
+            return;
+        }
+        let Some(question) = response.question.first() else { return };
+        let Ok(chain) = response.chain() else { return };
+        for alias in chain.aliases {
+            // synthesized from a DNAME, which is not cached itself
+            if response.answer.contains(&alias) {
+                self.insert_at(vec![alias], now);
+            }
+        }
+        if !chain.records.is_empty() {
+            self.insert_at(chain.records, now);
+            return;
+        }
+        // the SOA record of the zone of the name proves the negative answer
+        let soa = response
+            .authority
+            .iter()
+            .find(|r| r.rtype == RecordType::SOA && r.rclass == question.qclass() && chain.target.is_subdomain_of(&r.name));
+        let Some(soa) = soa else { return };
+        let negative = match rcode {
+            Rcode::NXDomain => Negative::NxDomain(soa.clone()),
+            _ => Negative::NoData(soa.clone()),
+        };
+        self.insert_negative_at(&chain.target, question.qtype(), question.qclass(), negative, now);
+    }
+
+    fn answer_at(&self, query: &Message, now: Instant) -> Option<Message> {
+        if !is_plain_query(query) {
+            return None;
+        }
+        let question = query.question.first()?;
+        let mut response = Message::new();
+        response.header = query.header.clone();
+        response.header.set_qr(true);
+        response.header.set_ra(true);
+        response.question = vec![question.clone()];
+        let mut name = question.qname.clone();
+        for _ in 0..=MAX_CHAIN_LEN {
+            if let Some(records) = self.get_at(&Key::RRset(name.clone(), question.qtype(), question.qclass()), now) {
+                response.answer.extend(records);
+                return Some(counted(response));
+            }
+            if let Some(negative) = self.negative_at(&name, question.qtype(), question.qclass(), now) {
+                if let Negative::NxDomain(_) = negative {
+                    response.header.set_rcode(Rcode::NXDomain);
+                }
+                response.authority.push(negative.soa().clone());
+                return Some(counted(response));
+            }
+            let alias = self.get_at(&Key::RRset(name.clone(), RecordType::CNAME, question.qclass()), now)?;
+            let RData::CNAME(target) = alias[0].data().ok()? else { return None };
+            response.answer.extend(alias);
+            name = target;
+        }
+        None
+    }
+}
+impl Entries {
+    /// Marks the key as used now, returning the time of use.
+    fn touch(&mut self, key: &Key) -> u64 {
+        if let Some(entry) = self.entries.get(key) {
+            let used = entry.used;
+            self.recent.remove(&used);
+        }
+        self.clock += 1;
+        self.recent.insert(self.clock, key.clone());
+        self.clock
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Edns, Soa};
+    use std::{sync::Arc, thread};
+
+    fn record(name: &str, ttl: u32, data: RData) -> Resource {
+        Resource::new(name.parse().unwrap(), ttl, data).unwrap()
+    }
+
+    fn key(name: &str, rtype: RecordType) -> Key {
+        Key::RRset(name.parse().unwrap(), rtype, DnsClass::IN)
+    }
+
+    #[test]
+    fn cache_counts_down_ttls() {
+        let cache = Cache::new(10);
+        let now = Instant::now();
+        let records = vec![
+            record("example.com", 300, RData::A([192, 0, 2, 1].into())),
+            record("example.com", 200, RData::A([192, 0, 2, 2].into())),
+        ];
+        cache.insert_at(records, now);
+
+        let records = cache.get_at(&key("EXAMPLE.com", RecordType::A), now + Duration::from_secs(50)).unwrap();
+        assert_eq!(records.len(), 2);
+        // the lowest TTL applies to the RRset
+        assert!(records.iter().all(|r| r.ttl == 150));
+        assert!(cache.get_at(&key("example.com", RecordType::A), now + Duration::from_secs(200)).is_none());
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn cache_clamps_ttls() {
+        let mut cache = Cache::new(10);
+        cache.set_min_ttl(60);
+        cache.set_max_ttl(3600);
+        let now = Instant::now();
+        cache.insert_at(vec![record("short.example", 0, RData::A([192, 0, 2, 1].into()))], now);
+        cache.insert_at(vec![record("long.example", 604800, RData::A([192, 0, 2, 1].into()))], now);
+
+        assert_eq!(cache.get_at(&key("short.example", RecordType::A), now).unwrap()[0].ttl, 60);
+        assert_eq!(cache.get_at(&key("long.example", RecordType::A), now).unwrap()[0].ttl, 3600);
+
+        // without a minimum, a zero TTL is not cached
+        let cache = Cache::new(10);
+        cache.insert_at(vec![record("short.example", 0, RData::A([192, 0, 2, 1].into()))], now);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn cache_evicts_least_recently_used() {
+        let cache = Cache::new(2);
+        let now = Instant::now();
+        for name in ["a.example", "b.example"] {
+            cache.insert_at(vec![record(name, 300, RData::A([192, 0, 2, 1].into()))], now);
+        }
+        // a is used after b
+        assert!(cache.get_at(&key("a.example", RecordType::A), now).is_some());
+        cache.insert_at(vec![record("c.example", 300, RData::A([192, 0, 2, 1].into()))], now);
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get_at(&key("b.example", RecordType::A), now).is_none());
+        assert!(cache.get_at(&key("a.example", RecordType::A), now).is_some());
+        assert!(cache.get_at(&key("c.example", RecordType::A), now).is_some());
+    }
+
+    #[test]
+    fn cache_answers_from_stored_chains() {
+        let cache = Cache::new(10);
+        let mut query = Message::new();
+        query.set_questions(vec!["www.example.com".to_string()]).unwrap();
+        let mut response = query.clone();
+        response.header.set_qr(true);
+        response.answer = vec![
+            record("www.example.com", 300, RData::CNAME("web.example.net".parse().unwrap())),
+            record("web.example.net", 60, RData::A([192, 0, 2, 1].into())),
+            // not on the chain
+            record("bank.example", 300, RData::A([192, 0, 2, 66].into())),
+        ];
+        cache.insert_response(&response);
+        assert_eq!(cache.len(), 2);
+
+        let cached = cache.answer(&query).unwrap();
+        assert!(cached.is_response_to(&query));
+        assert_eq!(cached.answer.len(), 2);
+        assert_eq!((cached.header.ancount, cached.header.nscount, cached.header.arcount), (2, 0, 0));
+        assert_eq!(cached.chain().unwrap().target.to_string(), "web.example.net");
+
+        query.question[0].set_qtype(RecordType::AAAA);
+        assert!(cache.answer(&query).is_none());
+    }
+
+    /// A negative response for `name` with the SOA record of `zone`.
+    fn negative(name: &str, rtype: RecordType, rcode: Rcode, zone: &str) -> Message {
+        let mut response = Message::new();
+        response.set_questions(vec![name.to_string()]).unwrap();
+        response.question[0].set_qtype(rtype);
+        response.header.set_qr(true);
+        response.header.set_rcode(rcode);
+        let soa = Soa {
+            mname: "ns.example.com".parse().unwrap(),
+            rname: "admin.example.com".parse().unwrap(),
+            serial: 1,
+            refresh: 3600,
+            retry: 600,
+            expire: 86400,
+            minimum: 300,
+        };
+        response.authority.push(record(zone, 3600, RData::SOA(soa)));
+        response
+    }
+
+    #[test]
+    fn cache_keeps_negative_answers() {
+        let cache = Cache::new(10);
+        let now = Instant::now();
+        let nxdomain = negative("missing.example.com", RecordType::A, Rcode::NXDomain, "example.com");
+        cache.insert_response_at(&nxdomain, now);
+        let nodata = negative("www.example.com", RecordType::AAAA, Rcode::NoError, "example.com");
+        cache.insert_response_at(&nodata, now);
+        assert_eq!(cache.len(), 2);
+
+        // NXDOMAIN holds for every type, for the SOA minimum
+        let name = "missing.example.com".parse().unwrap();
+        let later = now + Duration::from_secs(100);
+        match cache.negative_at(&name, RecordType::MX, DnsClass::IN, later) {
+            Some(Negative::NxDomain(soa)) => {
+                assert_eq!(soa.ttl, 200);
+                assert_eq!(soa.data().unwrap(), nxdomain.authority[0].data().unwrap());
+            }
+            other => panic!("expected NXDOMAIN, got {other:?}"),
+        }
+        assert!(cache.negative_at(&name, RecordType::A, DnsClass::IN, now + Duration::from_secs(300)).is_none());
+
+        // NODATA only for the type
+        let mut query = Message::new();
+        query.set_questions(vec!["www.example.com".to_string()]).unwrap();
+        query.question[0].set_qtype(RecordType::AAAA);
+        query.set_edns(Edns::new()).unwrap();
+        let cached = cache.answer_at(&query, now).unwrap();
+        assert!(cached.is_response_to(&query));
+        assert_eq!(cached.rcode(), Rcode::NoError);
+        assert!(cached.answer.is_empty());
+        assert_eq!((cached.header.ancount, cached.header.nscount, cached.header.arcount), (0, 1, 0));
+        assert_eq!(cached.authority[0].name.to_string(), "example.com");
+        query.question[0].set_qtype(RecordType::A);
+        assert!(cache.answer_at(&query, now).is_none());
+    }
+
+    #[test]
+    fn cache_keeps_negative_answers_at_the_end_of_chains() {
+        let cache = Cache::new(10);
+        let mut response = negative("alias.example.com", RecordType::A, Rcode::NXDomain, "example.net");
+        response.answer.push(record("alias.example.com", 300, RData::CNAME("gone.example.net".parse().unwrap())));
+        cache.insert_response(&response);
+
+        let mut query = Message::new();
+        query.set_questions(vec!["alias.example.com".to_string()]).unwrap();
+        let cached = cache.answer(&query).unwrap();
+        assert_eq!(cached.rcode(), Rcode::NXDomain);
+        assert_eq!(cached.answer.len(), 1);
+        assert!(cache.get_negative(&"gone.example.net".parse().unwrap(), RecordType::TXT, DnsClass::IN).is_some());
+
+        // without the SOA record of its zone a negative answer is not kept
+        let cache = Cache::new(10);
+        cache.insert_response(&negative("missing.example.com", RecordType::A, Rcode::NXDomain, "example.org"));
+        let mut response = negative("missing.example.com", RecordType::A, Rcode::NXDomain, "example.com");
+        response.authority.clear();
+        cache.insert_response(&response);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn cache_is_shared_between_threads() {
+        let cache = Arc::new(Cache::new(1000));
+        let threads: Vec<_> = (0..8)
+            .map(|i| {
+                let cache = cache.clone();
+                thread::spawn(move || {
+                    for j in 0..100 {
+                        let name = format!("host{j}.example");
+                        cache.insert(vec![record(&name, 300, RData::A([192, 0, 2, i].into()))]);
+                        assert!(cache.get(&name.parse().unwrap(), RecordType::A, DnsClass::IN).is_some());
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(cache.len(), 100);
+    }
+}
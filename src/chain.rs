@@ -0,0 +1,190 @@
+use crate::{dns_error::DnsError, DnsClass, Name, RData, RecordType, Resource};
+
+/// The most aliases followed for one question, real or synthesized.
+pub const MAX_CHAIN_LEN: usize = 16;
+
+/// The aliases from the name asked for to the records of the type asked
+/// for, as found in the answer section of one or more responses.
+///
+/// A DNAME (RFC 6672) that covers a name of the chain is followed as the
+/// CNAME it stands for, with the TTL of the DNAME, whether or not the
+/// server added that CNAME itself.
+///
+/// # Example
+///
+/// ```
+/// use dns::{Chain, Name, RData, RecordType, Resource};
+///
+/// let answer = vec![
+///     Resource::new("www.example.com".parse().unwrap(), 300, RData::CNAME("web.example.org".parse().unwrap())).unwrap(),
+///     Resource::new("example.org".parse().unwrap(), 300, RData::DNAME("example.net".parse().unwrap())).unwrap(),
+///     Resource::new("web.example.net".parse().unwrap(), 60, RData::A([192, 0, 2, 1].into())).unwrap(),
+/// ];
+///
+/// let mut chain = Chain::new("www.example.com".parse().unwrap());
+/// chain.follow(&answer, RecordType::A, dns::DnsClass::IN).unwrap();
+/// assert_eq!(chain.target.to_string(), "web.example.net");
+/// assert_eq!(chain.aliases.len(), 2);
+/// assert_eq!(chain.records.len(), 1);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chain {
+    /// the CNAME records that were followed, in order, including those
+    /// synthesized from DNAME records.
+    pub aliases: Vec<Resource>,
+    /// the name at the end of the chain, the canonical name.
+    pub target: Name,
+    /// the records of the type asked for at the target, empty if the
+    /// answer section ends before them.
+    pub records: Vec<Resource>,
+}
+impl Chain {
+    /// # Creates a chain that starts and ends at `name`
+    pub fn new(name: Name) -> Chain {
+        Chain {
+            aliases: vec![],
+            target: name.clone(),
+            records: vec![],
+        }
+    }
+
+    /// # Returns true if the records at the end of the chain were found
+    pub fn is_complete(&self) -> bool {
+        !self.records.is_empty()
+    }
+
+    /// # Follows the chain from its target through the records of `answer`
+    ///
+    /// Stops at the records of `rtype` and `class` at the target, or at the
+    /// first name that neither has them nor is an alias in `answer`.  An
+    /// alias of the type asked for, like a CNAME for a CNAME query, is not
+    /// followed.  Can be called again with the answer to a query for the
+    /// target to continue the chain.
+    ///
+    /// # Errors
+    ///
+    /// returns [`DnsError::AliasLoop`] if an alias leads back to a name of
+    /// the chain and [`DnsError::ChainTooLong`] after [`MAX_CHAIN_LEN`]
+    /// aliases.
+    pub fn follow(&mut self, answer: &[Resource], rtype: RecordType, class: DnsClass) -> Result<(), DnsError> {
+        loop {
+            let records: Vec<Resource> = answer
+                .iter()
+                .filter(|r| r.rtype == rtype && r.rclass == class && r.name == self.target)
+                .cloned()
+                .collect();
+            if !records.is_empty() {
+                self.records = records;
+                return Ok(());
+            }
+            let Some(alias) = self.alias(answer, class)? else { return Ok(()) };
+            let RData::CNAME(next) = alias.data()? else { unreachable!("aliases are CNAME records") };
+            if self.aliases.len() >= MAX_CHAIN_LEN {
+                return Err(DnsError::ChainTooLong(MAX_CHAIN_LEN));
+            }
+            self.aliases.push(alias);
+            if self.aliases.iter().any(|alias| alias.name == next) {
+                return Err(DnsError::AliasLoop(next));
+            }
+            self.target = next;
+        }
+    }
+
+    /// Returns the CNAME of the target, or the one a DNAME above it stands
+    /// for.
+    fn alias(&self, answer: &[Resource], class: DnsClass) -> Result<Option<Resource>, DnsError> {
+        let in_class = |r: &&Resource| r.rclass == class;
+        if let Some(cname) = answer.iter().filter(in_class).find(|r| r.rtype == RecordType::CNAME && r.name == self.target) {
+            return Ok(Some(cname.clone()));
+        }
+        // the DNAME closest to the target applies
+        let dname = answer
+            .iter()
+            .filter(in_class)
+            .filter(|r| r.rtype == RecordType::DNAME && r.name != self.target && self.target.is_subdomain_of(&r.name))
+            .max_by_key(|r| r.name.label_count());
+        let Some(dname) = dname else { return Ok(None) };
+        let RData::DNAME(redirect) = dname.data()? else { unreachable!("the type is DNAME") };
+        let prefix = self.target.label_count() - dname.name.label_count();
+        let next = Name::from_labels(self.target.labels().take(prefix).chain(redirect.labels()))?;
+        Ok(Some(Resource {
+            name: self.target.clone(),
+            rtype: RecordType::CNAME,
+            rclass: dname.rclass,
+            ttl: dname.ttl,
+            rdata: RData::CNAME(next).encode()?,
+            received: Default::default(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(name: &str, data: RData) -> Resource {
+        Resource::new(name.parse().unwrap(), 300, data).unwrap()
+    }
+
+    fn cname(name: &str, target: &str) -> Resource {
+        record(name, RData::CNAME(target.parse().unwrap()))
+    }
+
+    #[test]
+    fn chain_follows_cnames() {
+        let answer = vec![
+            record("c.example", RData::A([192, 0, 2, 1].into())),
+            cname("a.example", "b.example"),
+            cname("b.example", "c.example"),
+        ];
+        let mut chain = Chain::new("a.example".parse().unwrap());
+        chain.follow(&answer, RecordType::A, DnsClass::IN).unwrap();
+        assert!(chain.is_complete());
+        assert_eq!(chain.target.to_string(), "c.example");
+        assert_eq!(chain.aliases, answer[1..].to_vec());
+
+        // asking for the CNAME itself stops at it
+        let mut chain = Chain::new("a.example".parse().unwrap());
+        chain.follow(&answer, RecordType::CNAME, DnsClass::IN).unwrap();
+        assert_eq!(chain.records, vec![answer[1].clone()]);
+        assert!(chain.aliases.is_empty());
+    }
+
+    #[test]
+    fn chain_synthesizes_dname_rewrites() {
+        let answer = vec![
+            record("example.com", RData::DNAME("example.net".parse().unwrap())),
+            record("sub.example.com", RData::DNAME("sub.example.org".parse().unwrap())),
+        ];
+        let mut chain = Chain::new("www.sub.example.com".parse().unwrap());
+        chain.follow(&answer, RecordType::A, DnsClass::IN).unwrap();
+        // the closest DNAME wins, the chain leaves the answer
+        assert!(!chain.is_complete());
+        assert_eq!(chain.target.to_string(), "www.sub.example.org");
+        assert_eq!(chain.aliases, vec![cname("www.sub.example.com", "www.sub.example.org")]);
+
+        // the owner of a DNAME is not redirected
+        let mut chain = Chain::new("example.com".parse().unwrap());
+        chain.follow(&answer, RecordType::A, DnsClass::IN).unwrap();
+        assert!(chain.aliases.is_empty());
+    }
+
+    #[test]
+    fn chain_detects_loops_and_long_chains() {
+        let answer = vec![cname("a.example", "b.example"), cname("b.example", "A.example")];
+        let mut chain = Chain::new("a.example".parse().unwrap());
+        assert!(matches!(chain.follow(&answer, RecordType::A, DnsClass::IN), Err(DnsError::AliasLoop(_))));
+
+        // a DNAME that redirects into its own subtree
+        let answer = vec![record("example", RData::DNAME("x.example".parse().unwrap()))];
+        let mut chain = Chain::new("a.example".parse().unwrap());
+        assert!(matches!(
+            chain.follow(&answer, RecordType::A, DnsClass::IN),
+            Err(DnsError::ChainTooLong(MAX_CHAIN_LEN))
+        ));
+
+        let answer: Vec<Resource> = (0..=MAX_CHAIN_LEN).map(|i| cname(&format!("{i}.example"), &format!("{}.example", i + 1))).collect();
+        let mut chain = Chain::new("0.example".parse().unwrap());
+        assert!(matches!(chain.follow(&answer, RecordType::A, DnsClass::IN), Err(DnsError::ChainTooLong(_))));
+    }
+}
@@ -0,0 +1,889 @@
+use std::{
+    borrow::Cow,
+    fmt,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use crate::{dns_error::DnsError, transport, Cache, Edns, Message, Name, Rcode, ResolvConf, Transport};
+#[cfg(feature = "https")]
+use crate::DohConfig;
+#[cfg(feature = "tls")]
+use crate::TlsConfig;
+
+/// The transport a [`Client`] sends queries with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Protocol {
+    /// UDP, repeating the query over TCP if the reply is truncated.
+    Udp,
+    /// TCP for every query (RFC 7766), on connections that are kept open
+    /// between queries, see [`TcpTransport`](crate::TcpTransport).
+    Tcp,
+    /// DNS over TLS (RFC 7858), authenticated as set by
+    /// [`ClientConfig::set_tls()`].  The servers usually listen on port 853.
+    #[cfg(feature = "tls")]
+    Tls,
+    /// DNS over HTTPS (RFC 8484) as set by [`ClientConfig::set_doh()`].  The
+    /// host of the URI template is reached at the server addresses, the
+    /// port comes from the template.
+    ///
+    /// Called from async code the exchange runs on a thread of its own, the
+    /// calling thread still blocks until the reply arrives, see
+    /// [`crate::DohClient`].
+    #[cfg(feature = "https")]
+    Https,
+    /// DNS over QUIC (RFC 9250), authenticated as set by
+    /// [`ClientConfig::set_tls()`].  The servers usually listen on port 853.
+    ///
+    /// The connections run on a tokio runtime of their own, so a [`Client`]
+    /// may use them from async code too, but like with the other protocols
+    /// the calling thread blocks until the reply arrives.  Async code should
+    /// call it through `tokio::task::spawn_blocking`.
+    #[cfg(feature = "quic")]
+    Quic,
+}
+
+/// The settings of a [`Client`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientConfig {
+    /// the upstream servers, tried in order.
+    servers: Vec<SocketAddr>,
+    /// domains appended to names with fewer than `ndots` dots.
+    search: Vec<Name>,
+    /// see [`ClientConfig::candidate_names()`].
+    ndots: u8,
+    /// start with a different server for every query.
+    rotate: bool,
+    /// add an OPT record to queries that have none.
+    edns0: bool,
+    /// how long the first round waits for each server.
+    timeout: Duration,
+    /// how many rounds over all servers are made.
+    attempts: u8,
+    /// the transport queries are sent with.
+    protocol: Protocol,
+    /// how DNS over TLS servers are authenticated.
+    #[cfg(feature = "tls")]
+    tls: TlsConfig,
+    /// how DNS over HTTPS requests are made.
+    #[cfg(feature = "https")]
+    doh: DohConfig,
+}
+impl ClientConfig {
+    /// # Creates a new ClientConfig
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use dns::ClientConfig;
+    ///
+    /// let mut config = ClientConfig::new();
+    /// config.set_servers(vec!["10.0.0.53:53".parse().unwrap()]);
+    /// config.add_server("[fd00::53]:53".parse().unwrap());
+    /// assert_eq!(config.servers().len(), 2);
+    /// ```
+    ///
+    /// # Note
+    ///
+    /// This method creates a new ClientConfig with the following default values:
+    ///
+    /// - servers: 1.1.1.1:53
+    /// - search: Vec::new()
+    /// - ndots: 1
+    /// - rotate: false
+    /// - edns0: false
+    /// - timeout: 5 seconds
+    /// - attempts: 2
+    /// - protocol: Protocol::Udp
+    pub fn new() -> ClientConfig {
+        ClientConfig {
+            servers: vec![SocketAddr::new(IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)), 53)],
+            search: vec![],
+            ndots: 1,
+            rotate: false,
+            edns0: false,
+            timeout: Duration::from_secs(5),
+            attempts: 2,
+            protocol: Protocol::Udp,
+            #[cfg(feature = "tls")]
+            tls: TlsConfig::new(),
+            #[cfg(feature = "https")]
+            doh: default_doh(),
+        }
+    }
+
+    /// # Creates a ClientConfig from a resolver configuration
+    ///
+    /// # Note
+    ///
+    /// Without any `nameserver` line the local server at 127.0.0.1 is used,
+    /// like the system resolver does.
+    pub fn from_resolv_conf(conf: &ResolvConf) -> ClientConfig {
+        let mut servers = conf.nameservers.clone();
+        if servers.is_empty() {
+            servers.push(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 53));
+        }
+        ClientConfig {
+            servers,
+            search: conf.search_list(),
+            ndots: conf.ndots,
+            rotate: conf.rotate,
+            edns0: conf.edns0,
+            timeout: conf.timeout,
+            attempts: conf.attempts,
+            protocol: if conf.use_vc { Protocol::Tcp } else { Protocol::Udp },
+            #[cfg(feature = "tls")]
+            tls: TlsConfig::new(),
+            #[cfg(feature = "https")]
+            doh: default_doh(),
+        }
+    }
+
+    /// # Creates a ClientConfig from `/etc/resolv.conf`
+    ///
+    /// # Note
+    ///
+    /// Falls back to [`ClientConfig::new()`] if the file can not be read,
+    /// for example on systems without one.
+    pub fn system() -> ClientConfig {
+        match ResolvConf::system() {
+            Ok(conf) => ClientConfig::from_resolv_conf(&conf),
+            Err(_) => ClientConfig::new(),
+        }
+    }
+
+    /// # Replaces the upstream servers
+    pub fn set_servers(&mut self, servers: Vec<SocketAddr>) {
+        self.servers = servers;
+    }
+
+    /// # Adds an upstream server after the existing ones
+    pub fn add_server(&mut self, server: SocketAddr) {
+        self.servers.push(server);
+    }
+
+    /// # Returns the upstream servers
+    pub fn servers(&self) -> &[SocketAddr] {
+        &self.servers
+    }
+
+    /// # Replaces the search list
+    pub fn set_search(&mut self, search: Vec<Name>) {
+        self.search = search;
+    }
+
+    /// # Returns the search list
+    pub fn search(&self) -> &[Name] {
+        &self.search
+    }
+
+    /// # Sets the number of dots that make a name be tried as given first
+    pub fn set_ndots(&mut self, ndots: u8) {
+        self.ndots = ndots;
+    }
+
+    /// # Returns the number of dots that make a name be tried as given first
+    pub fn ndots(&self) -> u8 {
+        self.ndots
+    }
+
+    /// # Enables starting with a different server for every query
+    pub fn set_rotate(&mut self, rotate: bool) {
+        self.rotate = rotate;
+    }
+
+    /// # Returns true if queries are spread over all servers
+    pub fn rotate(&self) -> bool {
+        self.rotate
+    }
+
+    /// # Enables adding an EDNS(0) OPT record to queries without one
+    pub fn set_edns0(&mut self, edns0: bool) {
+        self.edns0 = edns0;
+    }
+
+    /// # Returns true if an OPT record is added to queries
+    pub fn edns0(&self) -> bool {
+        self.edns0
+    }
+
+    /// # Sets how long the first round waits for each server
+    ///
+    /// # Note
+    ///
+    /// Every further round waits twice as long as the one before.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// # Returns how long the first round waits for each server
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// # Sets how many rounds over all servers are made
+    ///
+    /// # Note
+    ///
+    /// Zero is treated as one, a query is always sent at least once.
+    pub fn set_attempts(&mut self, attempts: u8) {
+        self.attempts = attempts.max(1);
+    }
+
+    /// # Returns how many rounds over all servers are made
+    pub fn attempts(&self) -> u8 {
+        self.attempts
+    }
+
+    /// # Sets the transport queries are sent with
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use dns::{ClientConfig, Protocol};
+    ///
+    /// let mut config = ClientConfig::new();
+    /// config.set_protocol(Protocol::Tcp);
+    /// assert_eq!(config.protocol(), Protocol::Tcp);
+    /// ```
+    pub fn set_protocol(&mut self, protocol: Protocol) {
+        self.protocol = protocol;
+    }
+
+    /// # Returns the transport queries are sent with
+    pub fn protocol(&self) -> Protocol {
+        self.protocol
+    }
+
+    /// # Sets how DNS over TLS servers are authenticated
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use dns::{ClientConfig, Protocol, TlsConfig};
+    ///
+    /// let mut tls = TlsConfig::new();
+    /// tls.set_server_name("one.one.one.one");
+    ///
+    /// let mut config = ClientConfig::new();
+    /// config.set_servers(vec!["1.1.1.1:853".parse().unwrap()]);
+    /// config.set_protocol(Protocol::Tls);
+    /// config.set_tls(tls);
+    /// ```
+    #[cfg(feature = "tls")]
+    pub fn set_tls(&mut self, tls: TlsConfig) {
+        self.tls = tls;
+    }
+
+    /// # Returns how DNS over TLS servers are authenticated
+    #[cfg(feature = "tls")]
+    pub fn tls(&self) -> &TlsConfig {
+        &self.tls
+    }
+
+    /// # Sets how DNS over HTTPS requests are made
+    ///
+    /// # Note
+    ///
+    /// The default template is `https://cloudflare-dns.com/dns-query{?dns}`,
+    /// which belongs to the default server.
+    #[cfg(feature = "https")]
+    pub fn set_doh(&mut self, doh: DohConfig) {
+        self.doh = doh;
+    }
+
+    /// # Returns how DNS over HTTPS requests are made
+    #[cfg(feature = "https")]
+    pub fn doh(&self) -> &DohConfig {
+        &self.doh
+    }
+
+    /// # Returns the names to try for a name given by a user
+    ///
+    /// # Arguments
+    ///
+    /// takes a name in presentation format.
+    ///
+    /// # Returns
+    ///
+    /// returns only the name itself if it ends with a dot.  Otherwise the
+    /// name with each search domain appended is added, after the name itself
+    /// if it has at least `ndots` dots, else before it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use dns::{ClientConfig, Name};
+    ///
+    /// let mut config = ClientConfig::new();
+    /// config.set_search(vec!["corp.example.com".parse().unwrap()]);
+    ///
+    /// let names: Vec<String> = config.candidate_names("wiki").unwrap().iter().map(Name::to_string).collect();
+    /// assert_eq!(names, vec!["wiki.corp.example.com", "wiki"]);
+    ///
+    /// let names: Vec<String> = config.candidate_names("example.org").unwrap().iter().map(Name::to_string).collect();
+    /// assert_eq!(names, vec!["example.org", "example.org.corp.example.com"]);
+    ///
+    /// assert_eq!(config.candidate_names("wiki.").unwrap().len(), 1);
+    /// ```
+    pub fn candidate_names(&self, name: &str) -> Result<Vec<Name>, DnsError> {
+        let absolute: Name = name.parse()?;
+        if name.ends_with('.') && !name.ends_with("\\.") {
+            return Ok(vec![absolute]);
+        }
+        let mut names: Vec<Name> = self.search.iter().filter_map(|domain| absolute.join(domain).ok()).collect();
+        if absolute.label_count().saturating_sub(1) >= self.ndots as usize {
+            names.insert(0, absolute);
+        } else {
+            names.push(absolute);
+        }
+        Ok(names)
+    }
+}
+impl ClientConfig {
+    /// Applies the per-query settings, like adding EDNS(0).
+    pub(crate) fn prepare<'a>(&self, message: &'a Message) -> Result<Cow<'a, Message>, DnsError> {
+        if !self.edns0 || message.edns()?.is_some() {
+            return Ok(Cow::Borrowed(message));
+        }
+        let mut message = message.clone();
+        message.set_edns(Edns::new())?;
+        Ok(Cow::Owned(message))
+    }
+
+    /// Returns the servers in the order they are tried for the next query,
+    /// `next` counts the queries when rotating.
+    pub(crate) fn server_order(&self, next: &AtomicUsize) -> Vec<SocketAddr> {
+        let mut servers = self.servers.clone();
+        if self.rotate && !servers.is_empty() {
+            let start = next.fetch_add(1, Ordering::Relaxed) % servers.len();
+            servers.rotate_left(start);
+        }
+        servers
+    }
+
+    /// Returns how long to wait for each server in the zero based `round`.
+    pub(crate) fn round_timeout(&self, round: u8) -> Duration {
+        self.timeout.saturating_mul(1 << round.min(16))
+    }
+}
+#[cfg(feature = "https")]
+fn default_doh() -> DohConfig {
+    DohConfig::new("https://cloudflare-dns.com/dns-query{?dns}").expect("the template is a URL")
+}
+impl Default for ClientConfig {
+    fn default() -> Self {
+        ClientConfig::new()
+    }
+}
+
+/// Sends messages to the upstream servers of a [`ClientConfig`].
+///
+/// # Example
+///
+/// ```no_run
+/// use dns::{Client, ClientConfig, Message};
+///
+/// let mut config = ClientConfig::new();
+/// config.set_servers(vec!["10.0.0.53:53".parse().unwrap()]);
+/// let client = Client::new(config);
+///
+/// let mut message = Message::new();
+/// message.set_questions(vec!["www.google.com".to_string()]).unwrap();
+/// let response = client.send(&message).unwrap();
+/// ```
+pub struct Client {
+    config: ClientConfig,
+    /// the server to start with for the next query when rotating
+    next: AtomicUsize,
+    transport: Box<dyn Transport>,
+    cache: Option<Arc<Cache>>,
+}
+impl Client {
+    /// # Creates a new Client
+    ///
+    /// The queries are sent with the built-in transport of the
+    /// [`ClientConfig::protocol()`].
+    pub fn new(config: ClientConfig) -> Client {
+        let transport = transport::for_config(&config);
+        Client {
+            config,
+            next: AtomicUsize::new(0),
+            transport,
+            cache: None,
+        }
+    }
+
+    /// # Creates a new Client that sends the queries with `transport`
+    ///
+    /// The protocol of the `config` is ignored, everything else applies.
+    pub fn with_transport<T: Transport + 'static>(config: ClientConfig, transport: T) -> Client {
+        Client {
+            config,
+            next: AtomicUsize::new(0),
+            transport: Box::new(transport),
+            cache: None,
+        }
+    }
+
+    /// # Sets the cache the answers are kept in and taken from
+    ///
+    /// The cache can be shared with other clients.
+    pub fn set_cache(&mut self, cache: Arc<Cache>) {
+        self.cache = Some(cache);
+    }
+
+    /// # Returns the cache of this client
+    pub fn cache(&self) -> Option<&Arc<Cache>> {
+        self.cache.as_ref()
+    }
+
+    /// # Returns the settings of this client
+    pub fn config(&self) -> &ClientConfig {
+        &self.config
+    }
+
+    /// # Sends the message
+    ///
+    /// # Returns
+    ///
+    /// returns the response of the first server that answered.  The servers
+    /// are tried in turn for `attempts` rounds, each round waiting twice as
+    /// long as the one before, starting with `timeout`.
+    /// Replies that do not come from the server or do not answer the query
+    /// (see [`Message::is_response_to()`]) are ignored.  A server that
+    /// answers with SERVFAIL, REFUSED or NOTIMP is not asked again and the
+    /// next one is tried, such a response is only returned if no server
+    /// answered otherwise.
+    ///
+    /// Over [`Protocol::Udp`] a truncated reply makes the client ask the same
+    /// server again over TCP.
+    ///
+    /// With a cache (see [`Client::set_cache()`]) the question is answered
+    /// from it if it can, see [`Cache::answer()`], and the answers of the
+    /// servers are stored in it.
+    ///
+    /// # Errors
+    ///
+    /// returns [`DnsError::RetriesExhausted`] if no server answered in time,
+    /// or the error of the last server if some could not be reached at all.
+    pub fn send(&self, message: &Message) -> Result<Message, DnsError> {
+        let Some(cache) = &self.cache else { return self.send_to_servers(message) };
+        if let Some(response) = cache.answer(message) {
+            return Ok(response);
+        }
+        let response = self.send_to_servers(message)?;
+        cache.insert_response(&response);
+        Ok(response)
+    }
+}
+impl Client {
+    /// Sends the message to the servers in turn until one answers.
+    fn send_to_servers(&self, message: &Message) -> Result<Message, DnsError> {
+        let message = self.config.prepare(message)?;
+        let servers = self.server_order();
+        if servers.is_empty() {
+            return Err(DnsError::NoServers);
+        }
+        let attempts = self.config.attempts.max(1);
+        let mut last = None;
+        // the servers that answered with a failure and their last answer
+        let mut failed = vec![];
+        let mut failure = None;
+        for round in 0..attempts {
+            let timeout = self.config.round_timeout(round);
+            for server in servers.iter().filter(|server| !failed.contains(*server)).copied().collect::<Vec<_>>() {
+                match self.transport.send(server, &message, timeout) {
+                    Ok(response) if is_server_failure(&response) => {
+                        failed.push(server);
+                        failure = Some(response);
+                    }
+                    Ok(response) => return Ok(response),
+                    Err(DnsError::Timeout { .. }) => {}
+                    Err(e) => last = Some(e),
+                }
+            }
+        }
+        match failure {
+            Some(response) => Ok(response),
+            None => Err(last.unwrap_or(DnsError::RetriesExhausted { attempts })),
+        }
+    }
+
+    /// Returns the servers in the order they are tried for the next query.
+    fn server_order(&self) -> Vec<SocketAddr> {
+        self.config.server_order(&self.next)
+    }
+}
+/// Returns true if the server could or would not answer the query, which
+/// another server may do: SERVFAIL, REFUSED or NOTIMP.
+pub(crate) fn is_server_failure(response: &Message) -> bool {
+    matches!(response.rcode(), Rcode::ServFail | Rcode::Refused | Rcode::NotImp)
+}
+
+impl fmt::Debug for Client {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Client")
+            .field("config", &self.config)
+            .field("next", &self.next)
+            .field("cache", &self.cache)
+            .finish_non_exhaustive()
+    }
+}
+impl Default for Client {
+    fn default() -> Self {
+        Client::new(ClientConfig::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tcp, Opcode, RData, RecordType, Resource};
+    use std::{
+        net::{TcpListener, UdpSocket},
+        sync::Mutex,
+        thread,
+    };
+
+    /// Answers one query on a local socket by echoing it back as a response.
+    fn echo_server() -> (SocketAddr, thread::JoinHandle<()>) {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let mut buf = [0; 512];
+            let (amt, from) = socket.recv_from(&mut buf).unwrap();
+            let mut message = Message::parse(&buf[..amt]).unwrap();
+            message.header.set_qr(true);
+            socket.send_to(&message.encode().unwrap(), from).unwrap();
+        });
+        (addr, handle)
+    }
+
+    #[test]
+    fn client_sends_to_configured_server() {
+        let (addr, handle) = echo_server();
+        let mut config = ClientConfig::new();
+        config.set_servers(vec![addr]);
+
+        let mut message = Message::new();
+        message.set_questions(vec!["example.com".to_string()]).unwrap();
+        let response = Client::new(config).send(&message).unwrap();
+        handle.join().unwrap();
+
+        assert!(response.header.qr());
+        assert_eq!(response.header.id, message.header.id);
+        assert_eq!(response.question, message.question);
+    }
+
+    #[test]
+    fn client_adds_edns0_when_configured() {
+        let (addr, handle) = echo_server();
+        let mut config = ClientConfig::new();
+        config.set_servers(vec![addr]);
+        config.set_edns0(true);
+
+        let response = Client::new(config).send(&Message::new()).unwrap();
+        handle.join().unwrap();
+        assert_eq!(response.edns().unwrap(), Some(Edns::new()));
+    }
+
+    #[test]
+    fn client_rotates_servers() {
+        let servers: Vec<SocketAddr> = ["10.0.0.1:53", "10.0.0.2:53", "10.0.0.3:53"]
+            .iter()
+            .map(|s| s.parse().unwrap())
+            .collect();
+        let mut config = ClientConfig::new();
+        config.set_servers(servers.clone());
+        let client = Client::new(config.clone());
+        assert_eq!(client.server_order(), servers);
+        assert_eq!(client.server_order(), servers);
+
+        config.set_rotate(true);
+        let client = Client::new(config);
+        assert_eq!(client.server_order()[0], servers[0]);
+        assert_eq!(client.server_order()[0], servers[1]);
+        assert_eq!(client.server_order()[0], servers[2]);
+        assert_eq!(client.server_order()[0], servers[0]);
+    }
+
+    #[test]
+    fn client_config_from_resolv_conf() {
+        let conf = ResolvConf::parse("nameserver 10.0.0.53\nnameserver ::1\noptions rotate ndots:3\ndomain corp.example\n");
+        let config = ClientConfig::from_resolv_conf(&conf);
+        assert_eq!(config.servers(), &["10.0.0.53:53".parse().unwrap(), "[::1]:53".parse().unwrap()]);
+        assert_eq!(config.search(), &["corp.example".parse().unwrap()]);
+        assert!(config.rotate());
+        assert_eq!(config.ndots(), 3);
+
+        let config = ClientConfig::from_resolv_conf(&ResolvConf::new());
+        assert_eq!(config.servers(), &["127.0.0.1:53".parse().unwrap()]);
+    }
+
+    /// Ignores the first `drop` queries, then answers one like `echo_server`.
+    fn lossy_server(drop: usize) -> (SocketAddr, thread::JoinHandle<usize>) {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let mut buf = [0; 512];
+            for _ in 0..drop {
+                socket.recv_from(&mut buf).unwrap();
+            }
+            let (amt, from) = socket.recv_from(&mut buf).unwrap();
+            let mut message = Message::parse(&buf[..amt]).unwrap();
+            message.header.set_qr(true);
+            socket.send_to(&message.encode().unwrap(), from).unwrap();
+            drop + 1
+        });
+        (addr, handle)
+    }
+
+    fn quick_config(servers: Vec<SocketAddr>, attempts: u8) -> ClientConfig {
+        let mut config = ClientConfig::new();
+        config.set_servers(servers);
+        config.set_timeout(Duration::from_millis(50));
+        config.set_attempts(attempts);
+        config
+    }
+
+    #[test]
+    fn client_retransmits_after_timeout() {
+        let (addr, handle) = lossy_server(2);
+        let response = Client::new(quick_config(vec![addr], 3)).send(&Message::new());
+        assert!(response.unwrap().header.qr());
+        assert_eq!(handle.join().unwrap(), 3);
+    }
+
+    #[test]
+    fn client_fails_over_to_next_server() {
+        // a bound socket that never answers
+        let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let (addr, handle) = lossy_server(0);
+        let config = quick_config(vec![silent.local_addr().unwrap(), addr], 1);
+        assert!(Client::new(config).send(&Message::new()).unwrap().header.qr());
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn client_reports_exhausted_retries() {
+        let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let config = quick_config(vec![silent.local_addr().unwrap()], 2);
+        assert!(matches!(
+            Client::new(config).send(&Message::new()),
+            Err(DnsError::RetriesExhausted { attempts: 2 })
+        ));
+    }
+
+    /// Records the attempts and answers only the queries to `answering`.
+    struct MockTransport {
+        answering: SocketAddr,
+        refusing: Vec<SocketAddr>,
+        attempts: Mutex<Vec<(SocketAddr, Duration)>>,
+    }
+    impl Transport for MockTransport {
+        fn send(&self, server: SocketAddr, query: &Message, timeout: Duration) -> Result<Message, DnsError> {
+            self.attempts.lock().unwrap().push((server, timeout));
+            let mut response = query.clone();
+            response.header.set_qr(true);
+            if self.refusing.contains(&server) {
+                response.header.set_rcode(Rcode::Refused);
+                return Ok(response);
+            }
+            if server != self.answering {
+                return Err(DnsError::Timeout { server, timeout });
+            }
+            Ok(response)
+        }
+    }
+
+    #[test]
+    fn client_answers_from_cache() {
+        let server: SocketAddr = "192.0.2.1:53".parse().unwrap();
+        let mock = Arc::new(MockTransport {
+            answering: server,
+            refusing: vec![],
+            attempts: Mutex::new(vec![]),
+        });
+        let mut client = Client::with_transport(quick_config(vec![server], 1), mock.clone());
+        client.set_cache(Arc::new(Cache::new(10)));
+
+        let mut message = Message::new();
+        message.set_questions(vec!["example.com".to_string()]).unwrap();
+        message.answer.push(Resource::new("example.com".parse().unwrap(), 300, RData::A([192, 0, 2, 1].into())).unwrap());
+        client.send(&message).unwrap();
+        let response = client.send(&message).unwrap();
+        assert!(response.is_response_to(&message));
+        assert_eq!(response.answer.len(), 1);
+        assert_eq!(mock.attempts.lock().unwrap().len(), 1);
+
+        // the cache has no RRSIGs, no unvalidated data and only answers QUERY
+        let mut dnssec = message.clone();
+        let mut edns = Edns::new();
+        edns.dnssec_ok = true;
+        dnssec.set_edns(edns).unwrap();
+        let mut unchecked = message.clone();
+        unchecked.header.set_cd(true);
+        let mut notify = message.clone();
+        notify.header.set_opcode(Opcode::Notify);
+        for query in [dnssec, unchecked, notify] {
+            client.send(&query).unwrap();
+        }
+        assert_eq!(mock.attempts.lock().unwrap().len(), 4);
+        assert_eq!(client.cache().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn client_sends_with_custom_transport() {
+        let servers: Vec<SocketAddr> = vec!["192.0.2.1:53".parse().unwrap(), "192.0.2.2:53".parse().unwrap()];
+        let mock = Arc::new(MockTransport {
+            answering: servers[1],
+            refusing: vec![],
+            attempts: Mutex::new(vec![]),
+        });
+        let client = Client::with_transport(quick_config(servers.clone(), 2), mock.clone());
+        assert!(client.send(&Message::new()).unwrap().header.qr());
+        let timeout = Duration::from_millis(50);
+        assert_eq!(*mock.attempts.lock().unwrap(), vec![(servers[0], timeout), (servers[1], timeout)]);
+
+        // nobody answers, every server is tried in every round
+        let mock = Arc::new(MockTransport {
+            answering: "192.0.2.3:53".parse().unwrap(),
+            refusing: vec![],
+            attempts: Mutex::new(vec![]),
+        });
+        let client = Client::with_transport(quick_config(servers.clone(), 2), mock.clone());
+        assert!(matches!(client.send(&Message::new()), Err(DnsError::RetriesExhausted { attempts: 2 })));
+        let attempts = mock.attempts.lock().unwrap();
+        assert_eq!(
+            *attempts,
+            vec![(servers[0], timeout), (servers[1], timeout), (servers[0], timeout * 2), (servers[1], timeout * 2)]
+        );
+    }
+
+    #[test]
+    fn client_fails_over_on_error_responses() {
+        let servers: Vec<SocketAddr> = vec!["192.0.2.1:53".parse().unwrap(), "192.0.2.2:53".parse().unwrap()];
+        let timeout = Duration::from_millis(50);
+        let mock = Arc::new(MockTransport {
+            answering: servers[1],
+            refusing: vec![servers[0]],
+            attempts: Mutex::new(vec![]),
+        });
+        let client = Client::with_transport(quick_config(servers.clone(), 2), mock.clone());
+        assert_eq!(client.send(&Message::new()).unwrap().rcode(), Rcode::NoError);
+        assert_eq!(*mock.attempts.lock().unwrap(), vec![(servers[0], timeout), (servers[1], timeout)]);
+
+        // a refusing server is not asked again, its answer is the last resort
+        let mock = Arc::new(MockTransport {
+            answering: "192.0.2.3:53".parse().unwrap(),
+            refusing: vec![servers[0]],
+            attempts: Mutex::new(vec![]),
+        });
+        let client = Client::with_transport(quick_config(servers.clone(), 2), mock.clone());
+        assert_eq!(client.send(&Message::new()).unwrap().rcode(), Rcode::Refused);
+        assert_eq!(
+            *mock.attempts.lock().unwrap(),
+            vec![(servers[0], timeout), (servers[1], timeout), (servers[1], timeout * 2)]
+        );
+    }
+
+    #[test]
+    fn client_discards_forged_responses() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let mut buf = [0; 512];
+            let (amt, from) = socket.recv_from(&mut buf).unwrap();
+            let query = Message::parse(&buf[..amt]).unwrap();
+            let mut response = query.clone();
+            response.header.set_qr(true);
+
+            // from another address
+            let stranger = UdpSocket::bind("127.0.0.1:0").unwrap();
+            stranger.send_to(&response.encode().unwrap(), from).unwrap();
+            // garbage
+            socket.send_to(&[0xff; 3], from).unwrap();
+            // wrong ID
+            let mut forged = response.clone();
+            forged.header.set_id(query.header.id.wrapping_add(1));
+            socket.send_to(&forged.encode().unwrap(), from).unwrap();
+            // wrong question
+            let mut forged = response.clone();
+            forged.question[0].set_qtype(RecordType::TXT);
+            socket.send_to(&forged.encode().unwrap(), from).unwrap();
+            // not a response
+            socket.send_to(&query.encode().unwrap(), from).unwrap();
+
+            response.answer.push(Resource::new("example.com".parse().unwrap(), 60, RData::A([192, 0, 2, 1].into())).unwrap());
+            socket.send_to(&response.encode().unwrap(), from).unwrap();
+        });
+
+        let mut message = Message::new();
+        message.set_questions(vec!["example.com".to_string()]).unwrap();
+        let response = Client::new(quick_config(vec![addr], 1)).send(&message).unwrap();
+        handle.join().unwrap();
+        assert_eq!(response.answer.len(), 1);
+    }
+
+    /// Answers one query over TCP with an A record.
+    fn tcp_server(listener: TcpListener) -> thread::JoinHandle<()> {
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let query = Message::parse(&tcp::read_frame(&mut stream).unwrap()).unwrap();
+            let mut response = query.clone();
+            response.header.set_qr(true);
+            response.answer.push(Resource::new("example.com".parse().unwrap(), 60, RData::A([192, 0, 2, 1].into())).unwrap());
+            tcp::write_frame(&mut stream, &response.encode().unwrap()).unwrap();
+        })
+    }
+
+    #[test]
+    fn client_forces_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut config = quick_config(vec![listener.local_addr().unwrap()], 1);
+        config.set_protocol(Protocol::Tcp);
+        let handle = tcp_server(listener);
+
+        let mut message = Message::new();
+        message.set_questions(vec!["example.com".to_string()]).unwrap();
+        let response = Client::new(config).send(&message).unwrap();
+        handle.join().unwrap();
+        assert_eq!(response.answer.len(), 1);
+    }
+
+    #[test]
+    fn client_retries_truncated_replies_over_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let socket = UdpSocket::bind(addr).unwrap();
+        let udp = thread::spawn(move || {
+            let mut buf = [0; 512];
+            let (amt, from) = socket.recv_from(&mut buf).unwrap();
+            let mut message = Message::parse(&buf[..amt]).unwrap();
+            message.header.set_qr(true);
+            message.header.set_tc(true);
+            socket.send_to(&message.encode().unwrap(), from).unwrap();
+        });
+        let tcp = tcp_server(listener);
+
+        let mut message = Message::new();
+        message.set_questions(vec!["example.com".to_string()]).unwrap();
+        let response = Client::new(quick_config(vec![addr], 1)).send(&message).unwrap();
+        udp.join().unwrap();
+        tcp.join().unwrap();
+        assert!(!response.header.tc());
+        assert_eq!(response.answer.len(), 1);
+    }
+
+    #[test]
+    fn client_without_servers_fails() {
+        let mut config = ClientConfig::new();
+        config.set_servers(vec![]);
+        assert!(matches!(Client::new(config).send(&Message::new()), Err(DnsError::NoServers)));
+    }
+}
@@ -0,0 +1,99 @@
+use std::{fmt, str::FromStr};
+
+use crate::dns_error::DnsError;
+
+/// The CLASS of a resource record or the QCLASS of a question.
+///
+/// Values without a name in the IANA registry are kept as
+/// [`DnsClass::Unknown`] and written as `CLASSnnn` (RFC 3597).
+///
+/// # Example
+///
+/// ```
+/// use dns::DnsClass;
+///
+/// let class: DnsClass = "IN".parse().unwrap();
+/// assert_eq!(class, DnsClass::IN);
+/// assert_eq!(u16::from(class), 1);
+/// assert_eq!(DnsClass::from(65280).to_string(), "CLASS65280");
+/// assert_eq!("class3".parse::<DnsClass>().unwrap(), DnsClass::CH);
+/// assert!("CLASS+3".parse::<DnsClass>().is_err());
+/// ```
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DnsClass {
+    /// the Internet
+    IN,
+    /// the CHAOS class
+    CH,
+    /// Hesiod
+    HS,
+    /// QCLASS NONE, used by dynamic updates (RFC 2136)
+    NONE,
+    /// QCLASS * (ANY)
+    ANY,
+    /// A class without a mnemonic.
+    Unknown(u16),
+}
+impl From<u16> for DnsClass {
+    fn from(value: u16) -> Self {
+        match value {
+            1 => DnsClass::IN,
+            3 => DnsClass::CH,
+            4 => DnsClass::HS,
+            254 => DnsClass::NONE,
+            255 => DnsClass::ANY,
+            _ => DnsClass::Unknown(value),
+        }
+    }
+}
+impl From<DnsClass> for u16 {
+    fn from(value: DnsClass) -> Self {
+        match value {
+            DnsClass::IN => 1,
+            DnsClass::CH => 3,
+            DnsClass::HS => 4,
+            DnsClass::NONE => 254,
+            DnsClass::ANY => 255,
+            DnsClass::Unknown(value) => value,
+        }
+    }
+}
+
+/// # Parses a class mnemonic
+///
+/// Mnemonics are case-insensitive, `CHAOS` and `HESIOD` are accepted as
+/// well as `*` for ANY and `CLASSnnn` for any numeric class.
+impl FromStr for DnsClass {
+    type Err = DnsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let class = match s.to_ascii_uppercase().as_str() {
+            "IN" => DnsClass::IN,
+            "CH" | "CHAOS" => DnsClass::CH,
+            "HS" | "HESIOD" => DnsClass::HS,
+            "NONE" => DnsClass::NONE,
+            "ANY" | "*" => DnsClass::ANY,
+            upper => upper
+                .strip_prefix("CLASS")
+                .filter(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+                .and_then(|n| n.parse::<u16>().ok())
+                .map(DnsClass::from)
+                .ok_or_else(|| DnsError::InvalidClass(s.to_string()))?,
+        };
+        Ok(class)
+    }
+}
+
+impl fmt::Display for DnsClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DnsClass::IN => f.write_str("IN"),
+            DnsClass::CH => f.write_str("CH"),
+            DnsClass::HS => f.write_str("HS"),
+            DnsClass::NONE => f.write_str("NONE"),
+            DnsClass::ANY => f.write_str("ANY"),
+            DnsClass::Unknown(value) => write!(f, "CLASS{value}"),
+        }
+    }
+}
@@ -1,26 +1,149 @@
-use std::{error::Error, fmt};
+use std::{error::Error, fmt, io, net::SocketAddr, time::Duration};
+
+use crate::{Name, Protocol, Rcode, RecordType, Section};
 
 #[derive(Debug)]
 pub enum DnsError {
     InvalidZFlag(i32),
     InvalidOpcodeFlag(i32),
     InvalidRcodeFlag(i32),
-    InvalidQType(u16),
-    InvalidQClass(u16),
-    UdpSocketError(u16),
+    /// A record type mnemonic that is neither known nor of the form `TYPEnnn`.
+    InvalidRecordType(String),
+    /// A class mnemonic that is neither known nor of the form `CLASSnnn`.
+    InvalidClass(String),
+    UdpSocketError(io::Error),
+    /// The message ended before the field starting at `offset` was complete.
+    Truncated { offset: usize, section: Section },
+    /// A label length octet used one of the reserved `01` or `10` type bits.
+    InvalidLabelType { offset: usize, section: Section },
+    /// A compression pointer pointed at or after its own position.
+    InvalidPointer { offset: usize, section: Section },
+    /// A compression pointer pointed back into a name it is part of.
+    PointerLoop { offset: usize, section: Section },
+    /// A decoded name was longer than 255 octets.
+    NameTooLong { offset: usize, section: Section },
+    /// The RDATA starting at `offset` did not match its RDLENGTH.
+    InvalidRdata { offset: usize, section: Section },
+    /// The RDATA written at `offset` does not fit into the 16 bit RDLENGTH.
+    RdataTooLong { offset: usize, section: Section },
+    /// A section has more entries than its 16 bit count can hold.
+    TooManyRecords(Section),
+    /// A domain name could not be built, `reason` tells which rule it broke.
+    InvalidName { name: String, reason: &'static str },
+    /// RDATA that does not have the layout of its record type.
+    MalformedRdata(RecordType),
+    /// The additional section holds more than one OPT record.
+    DuplicateOpt,
+    /// The client has no upstream server configured.
+    NoServers,
+    /// A configuration file could not be read.
+    ConfigError(io::Error),
+    /// A server did not answer within the timeout of one attempt.
+    Timeout { server: SocketAddr, timeout: Duration },
+    /// No server answered in any of the attempts.
+    RetriesExhausted { attempts: u8 },
+    TcpSocketError(io::Error),
+    /// The message does not fit in the 16 bit length prefix of TCP.
+    MessageTooLong(usize),
+    /// The server closed the connection before it answered.
+    ConnectionClosed,
+    /// Every message ID is in use on the connection.
+    TooManyPending,
+    /// The TLS configuration is invalid or the TLS connection failed,
+    /// including a server that could not be authenticated.
+    TlsError(io::Error),
+    /// The DNS over HTTPS configuration is invalid or the request failed.
+    HttpsError(io::Error),
+    /// The DNS over HTTPS server answered with this status, or without a
+    /// DNS message.
+    HttpStatus(u16),
+    /// The reply does not answer the query.
+    MismatchedResponse,
+    /// The QUIC connection or stream failed.
+    QuicError(io::Error),
+    /// The client can not send queries with this protocol.
+    UnsupportedProtocol(Protocol),
+    /// The name does not exist (NXDOMAIN).
+    NxDomain(Name),
+    /// The name exists, but has no records of the type (NODATA).
+    NoData { name: Name, rtype: RecordType },
+    /// The server answered with an error, like SERVFAIL or REFUSED.
+    ErrorResponse(Rcode),
+    /// A CNAME or DNAME leads back to this name of the chain.
+    AliasLoop(Name),
+    /// The chain has more aliases than this limit.
+    ChainTooLong(usize),
+    /// The message has no question.
+    NoQuestion,
+    /// More referrals than this limit were needed to reach the answer.
+    TooManyReferrals(usize),
+    /// The resolution needed more queries than this limit.
+    TooManyQueries(usize),
+    /// A server referred to this zone, which is not below its own zone or
+    /// not above the name.
+    InvalidReferral(Name),
+    /// No address of a name server of this zone could be found.
+    NoNameServers(Name),
 }
 
-impl Error for DnsError {}
+impl Error for DnsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DnsError::UdpSocketError(e)
+            | DnsError::ConfigError(e)
+            | DnsError::TcpSocketError(e)
+            | DnsError::TlsError(e)
+            | DnsError::HttpsError(e)
+            | DnsError::QuicError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
 
 impl fmt::Display for DnsError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match *self {
+        match self {
             DnsError::InvalidZFlag(v) => write!(f, "Invalid Z flag {v} (should be 0)"),
-            DnsError::InvalidOpcodeFlag(v) => write!(f, "Invalid opcode flag {v} (should be 0-2)"),
-            DnsError::InvalidRcodeFlag(v) => write!(f, "Invalid rcode flag {v} (should be 0-5)"),
-            DnsError::InvalidQType(v) => write!(f, "Invalid qtype {v} (should be in https://en.wikipedia.org/wiki/List_of_DNS_record_types) (contact me if im wrong!)"),
-            DnsError::InvalidQClass(v) => write!(f, "Invalid qclass {v} (should be in rfc6895) (contact me if im wrong!)"),
+            DnsError::InvalidOpcodeFlag(v) => write!(f, "Invalid opcode flag {v} (should be 0-2 or 4-6)"),
+            DnsError::InvalidRcodeFlag(v) => write!(f, "Invalid rcode flag {v} (should be 0-11)"),
+            DnsError::InvalidRecordType(v) => write!(f, "Invalid record type {v:?} (should be a mnemonic like AAAA or TYPEnnn)"),
+            DnsError::InvalidClass(v) => write!(f, "Invalid class {v:?} (should be a mnemonic like IN or CLASSnnn)"),
             DnsError::UdpSocketError(v) => write!(f, "UdpSocket returned Error: {v}"),
+            DnsError::Truncated { offset, section } => write!(f, "Message truncated at byte {offset} in the {section} section"),
+            DnsError::InvalidLabelType { offset, section } => write!(f, "Invalid label type at byte {offset} in the {section} section"),
+            DnsError::InvalidPointer { offset, section } => write!(f, "Invalid compression pointer at byte {offset} in the {section} section"),
+            DnsError::PointerLoop { offset, section } => write!(f, "Compression pointer loop at byte {offset} in the {section} section"),
+            DnsError::NameTooLong { offset, section } => write!(f, "Name at byte {offset} in the {section} section is longer than 255 octets"),
+            DnsError::InvalidRdata { offset, section } => write!(f, "RDATA at byte {offset} in the {section} section does not match its length"),
+            DnsError::RdataTooLong { offset, section } => write!(f, "RDATA at byte {offset} in the {section} section is longer than 65535 octets"),
+            DnsError::TooManyRecords(section) => write!(f, "The {section} section has more than 65535 entries"),
+            DnsError::InvalidName { name, reason } => write!(f, "Invalid name {name:?}: {reason}"),
+            DnsError::MalformedRdata(rtype) => write!(f, "Malformed RDATA for a {rtype} record"),
+            DnsError::DuplicateOpt => write!(f, "More than one OPT record in the additional section"),
+            DnsError::NoServers => write!(f, "No upstream server configured"),
+            DnsError::ConfigError(v) => write!(f, "Could not read the configuration: {v}"),
+            DnsError::Timeout { server, timeout } => write!(f, "{server} did not answer within {timeout:?}"),
+            DnsError::RetriesExhausted { attempts } => write!(f, "No server answered after {attempts} attempts"),
+            DnsError::TcpSocketError(v) => write!(f, "TcpStream returned Error: {v}"),
+            DnsError::MessageTooLong(v) => write!(f, "Message of {v} bytes is too long"),
+            DnsError::ConnectionClosed => write!(f, "Connection closed by the server"),
+            DnsError::TooManyPending => write!(f, "Too many queries waiting for an answer"),
+            DnsError::TlsError(v) => write!(f, "TLS connection failed: {v}"),
+            DnsError::HttpsError(v) => write!(f, "HTTPS request failed: {v}"),
+            DnsError::HttpStatus(v) => write!(f, "HTTPS server answered with status {v}"),
+            DnsError::MismatchedResponse => write!(f, "The reply does not answer the query"),
+            DnsError::QuicError(v) => write!(f, "QUIC connection failed: {v}"),
+            DnsError::UnsupportedProtocol(v) => write!(f, "Protocol {v:?} is not supported by this client"),
+            DnsError::NxDomain(name) => write!(f, "{name} does not exist"),
+            DnsError::NoData { name, rtype } => write!(f, "{name} has no {rtype} records"),
+            DnsError::ErrorResponse(rcode) => write!(f, "The server answered with {rcode}"),
+            DnsError::AliasLoop(name) => write!(f, "The alias chain loops back to {name}"),
+            DnsError::ChainTooLong(v) => write!(f, "The alias chain is longer than {v} names"),
+            DnsError::NoQuestion => write!(f, "The message has no question"),
+            DnsError::TooManyReferrals(v) => write!(f, "More than {v} referrals to reach the answer"),
+            DnsError::TooManyQueries(v) => write!(f, "More than {v} queries to reach the answer"),
+            DnsError::InvalidReferral(zone) => write!(f, "Invalid referral to {zone}"),
+            DnsError::NoNameServers(zone) => write!(f, "No name server of {zone} could be found"),
         }
     }
 }
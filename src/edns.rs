@@ -0,0 +1,342 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::{dns_error::DnsError, wire::Reader, DnsClass, Name, RecordType, Resource};
+
+/// The DO bit in the flags of the OPT record.
+const DO_BIT: u16 = 0b1000_0000_0000_0000;
+
+/// The EDNS(0) parameters of a message (RFC 6891).
+///
+/// On the wire they travel as an OPT pseudo-record in the additional
+/// section, see [`crate::Message::edns()`] and [`crate::Message::set_edns()`].
+///
+/// # Example
+///
+/// ```
+/// use dns::{Edns, EdnsOption, Message};
+///
+/// let mut message = Message::new();
+/// let mut edns = Edns::new();
+/// edns.dnssec_ok = true;
+/// edns.options.push(EdnsOption::Nsid(vec![]));
+/// message.set_edns(edns.clone()).unwrap();
+///
+/// assert_eq!(message.edns().unwrap(), Some(edns));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edns {
+    /// the largest UDP payload the sender can reassemble.
+    pub udp_payload_size: u16,
+    /// the upper eight bits of the twelve bit response code.
+    pub extended_rcode: u8,
+    /// the EDNS version, only version 0 is defined.
+    pub version: u8,
+    /// the DNSSEC OK bit (RFC 3225).
+    pub dnssec_ok: bool,
+    /// the remaining 15 flag bits, which must be zero when sending.
+    pub z: u16,
+    /// the options in the RDATA of the OPT record.
+    pub options: Vec<EdnsOption>,
+}
+
+/// An option carried in the OPT record.
+///
+/// Options without a decoder, or with a layout that is not understood, are
+/// kept as [`EdnsOption::Unknown`] so they survive being re-encoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EdnsOption {
+    /// Name server identifier (RFC 5001), empty in queries.
+    Nsid(Vec<u8>),
+    /// Client subnet (RFC 7871).
+    ClientSubnet(ClientSubnet),
+    /// DNS cookie (RFC 7873), the server cookie is empty in queries.
+    Cookie { client: [u8; 8], server: Vec<u8> },
+    /// TCP keepalive (RFC 7828), the timeout is given in units of 100
+    /// milliseconds and is left out in queries.
+    TcpKeepalive(Option<u16>),
+    /// Padding (RFC 7830) with the given number of zero octets.
+    Padding(u16),
+    /// Extended DNS error (RFC 8914).
+    ExtendedError { info_code: u16, extra_text: String },
+    /// Any other option.
+    Unknown { code: u16, data: Vec<u8> },
+}
+
+/// The client subnet option (RFC 7871).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientSubnet {
+    /// the client address, only the first `source_prefix` bits are sent.
+    pub address: IpAddr,
+    /// the number of significant bits of `address`, at most 32 for IPv4
+    /// and 128 for IPv6.
+    pub source_prefix: u8,
+    /// the number of bits the answer covers, zero in queries.
+    pub scope_prefix: u8,
+}
+
+impl Edns {
+    /// # Creates a new Edns
+    ///
+    /// # Note
+    ///
+    /// This method creates a new Edns with the following default values:
+    ///
+    /// - udp_payload_size: 1232    // avoids IP fragmentation on most paths
+    /// - extended_rcode: 0
+    /// - version: 0
+    /// - dnssec_ok: false
+    /// - z: 0
+    /// - options: Vec::new()
+    pub fn new() -> Edns {
+        Edns {
+            udp_payload_size: 1232,
+            extended_rcode: 0,
+            version: 0,
+            dnssec_ok: false,
+            z: 0,
+            options: vec![],
+        }
+    }
+
+    /// # Reads the parameters from an OPT record
+    pub fn from_record(record: &Resource) -> Result<Edns, DnsError> {
+        if record.rtype != RecordType::OPT || !record.name.is_root() {
+            return Err(DnsError::MalformedRdata(RecordType::OPT));
+        }
+        let flags = record.ttl as u16;
+        let mut reader = Reader::new(&record.rdata);
+        let mut options = vec![];
+        while !reader.is_empty() {
+            let option = (|| {
+                let code = reader.u16()?;
+                let len = reader.u16()?;
+                Ok::<_, DnsError>(EdnsOption::decode(code, reader.bytes(len as usize)?))
+            })();
+            options.push(option.map_err(|_| DnsError::MalformedRdata(RecordType::OPT))?);
+        }
+        Ok(Edns {
+            udp_payload_size: u16::from(record.rclass),
+            extended_rcode: (record.ttl >> 24) as u8,
+            version: (record.ttl >> 16) as u8,
+            dnssec_ok: flags & DO_BIT != 0,
+            z: flags & !DO_BIT,
+            options,
+        })
+    }
+
+    /// # Builds the OPT record for these parameters
+    pub fn to_record(&self) -> Result<Resource, DnsError> {
+        let mut rdata = vec![];
+        for option in &self.options {
+            let data = option.encode()?;
+            let len = u16::try_from(data.len()).map_err(|_| DnsError::MalformedRdata(RecordType::OPT))?;
+            rdata.extend_from_slice(&option.code().to_be_bytes());
+            rdata.extend_from_slice(&len.to_be_bytes());
+            rdata.extend_from_slice(&data);
+        }
+        let flags = if self.dnssec_ok { DO_BIT } else { 0 } | (self.z & !DO_BIT);
+        Ok(Resource {
+            name: Name::root(),
+            rtype: RecordType::OPT,
+            rclass: DnsClass::from(self.udp_payload_size),
+            ttl: (self.extended_rcode as u32) << 24 | (self.version as u32) << 16 | flags as u32,
+            rdata,
+            received: Default::default(),
+        })
+    }
+}
+impl Default for Edns {
+    fn default() -> Self {
+        Edns::new()
+    }
+}
+
+impl EdnsOption {
+    /// # Returns the option code
+    pub fn code(&self) -> u16 {
+        match self {
+            EdnsOption::Nsid(_) => 3,
+            EdnsOption::ClientSubnet(_) => 8,
+            EdnsOption::Cookie { .. } => 10,
+            EdnsOption::TcpKeepalive(_) => 11,
+            EdnsOption::Padding(_) => 12,
+            EdnsOption::ExtendedError { .. } => 15,
+            EdnsOption::Unknown { code, .. } => *code,
+        }
+    }
+
+    /// Decodes an option, falling back to [`EdnsOption::Unknown`].
+    fn decode(code: u16, data: &[u8]) -> EdnsOption {
+        let option = match code {
+            3 => Some(EdnsOption::Nsid(data.to_vec())),
+            8 => ClientSubnet::decode(data).map(EdnsOption::ClientSubnet),
+            10 if data.len() == 8 || (16..=40).contains(&data.len()) => {
+                let mut client = [0; 8];
+                client.copy_from_slice(&data[..8]);
+                Some(EdnsOption::Cookie {
+                    client,
+                    server: data[8..].to_vec(),
+                })
+            }
+            11 => match data {
+                [] => Some(EdnsOption::TcpKeepalive(None)),
+                [a, b] => Some(EdnsOption::TcpKeepalive(Some(u16::from_be_bytes([*a, *b])))),
+                _ => None,
+            },
+            12 if data.iter().all(|b| *b == 0) => Some(EdnsOption::Padding(data.len() as u16)),
+            15 if data.len() >= 2 => std::str::from_utf8(&data[2..]).ok().map(|text| EdnsOption::ExtendedError {
+                info_code: u16::from_be_bytes([data[0], data[1]]),
+                extra_text: text.to_string(),
+            }),
+            _ => None,
+        };
+        option.unwrap_or_else(|| EdnsOption::Unknown {
+            code,
+            data: data.to_vec(),
+        })
+    }
+
+    fn encode(&self) -> Result<Vec<u8>, DnsError> {
+        let data = match self {
+            EdnsOption::Nsid(data) => data.clone(),
+            EdnsOption::ClientSubnet(subnet) => subnet.encode()?,
+            EdnsOption::Cookie { client, server } => [&client[..], server].concat(),
+            EdnsOption::TcpKeepalive(timeout) => timeout.map(|t| t.to_be_bytes().to_vec()).unwrap_or_default(),
+            EdnsOption::Padding(len) => vec![0; *len as usize],
+            EdnsOption::ExtendedError { info_code, extra_text } => {
+                [&info_code.to_be_bytes()[..], extra_text.as_bytes()].concat()
+            }
+            EdnsOption::Unknown { data, .. } => data.clone(),
+        };
+        Ok(data)
+    }
+}
+
+impl ClientSubnet {
+    fn decode(data: &[u8]) -> Option<ClientSubnet> {
+        if data.len() < 4 {
+            return None;
+        }
+        let (header, address) = data.split_at(4);
+        let family = u16::from_be_bytes([header[0], header[1]]);
+        let address = match family {
+            1 if address.len() <= 4 => {
+                let mut octets = [0; 4];
+                octets[..address.len()].copy_from_slice(address);
+                IpAddr::V4(Ipv4Addr::from(octets))
+            }
+            2 if address.len() <= 16 => {
+                let mut octets = [0; 16];
+                octets[..address.len()].copy_from_slice(address);
+                IpAddr::V6(Ipv6Addr::from(octets))
+            }
+            _ => return None,
+        };
+        Some(ClientSubnet {
+            address,
+            source_prefix: header[2],
+            scope_prefix: header[3],
+        })
+    }
+
+    fn encode(&self) -> Result<Vec<u8>, DnsError> {
+        let (family, mut octets) = match self.address {
+            IpAddr::V4(addr) => (1u16, addr.octets().to_vec()),
+            IpAddr::V6(addr) => (2u16, addr.octets().to_vec()),
+        };
+        let prefix = self.source_prefix as usize;
+        if prefix > octets.len() * 8 {
+            return Err(DnsError::MalformedRdata(RecordType::OPT));
+        }
+        // only the significant octets are sent, with the bits past the
+        // prefix cleared (RFC 7871 section 6)
+        octets.truncate(prefix.div_ceil(8));
+        let bits = prefix % 8;
+        if bits != 0 {
+            if let Some(last) = octets.last_mut() {
+                *last &= 0xff << (8 - bits);
+            }
+        }
+        let mut out = family.to_be_bytes().to_vec();
+        out.push(self.source_prefix);
+        out.push(self.scope_prefix);
+        out.extend_from_slice(&octets);
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edns_round_trips_through_opt_record() {
+        let edns = Edns {
+            udp_payload_size: 4096,
+            extended_rcode: 1,
+            version: 0,
+            dnssec_ok: true,
+            z: 0,
+            options: vec![
+                EdnsOption::Nsid(b"ns1".to_vec()),
+                EdnsOption::ClientSubnet(ClientSubnet {
+                    address: "192.0.2.0".parse().unwrap(),
+                    source_prefix: 24,
+                    scope_prefix: 0,
+                }),
+                EdnsOption::Cookie {
+                    client: [1; 8],
+                    server: vec![2; 16],
+                },
+                EdnsOption::TcpKeepalive(Some(300)),
+                EdnsOption::Padding(5),
+                EdnsOption::ExtendedError {
+                    info_code: 18,
+                    extra_text: "prohibited".to_string(),
+                },
+                EdnsOption::Unknown {
+                    code: 65001,
+                    data: vec![1, 2, 3],
+                },
+            ],
+        };
+        let record = edns.to_record().unwrap();
+        assert_eq!(record.ttl, 0x0100_8000);
+        assert_eq!(u16::from(record.rclass), 4096);
+        assert_eq!(&record.rdata[11..17], &[0, 1, 24, 0, 192, 0]);
+        assert_eq!(Edns::from_record(&record).unwrap(), edns);
+    }
+
+    #[test]
+    fn client_subnet_clears_bits_past_the_prefix() {
+        let subnet = |address: &str, source_prefix| ClientSubnet {
+            address: address.parse().unwrap(),
+            source_prefix,
+            scope_prefix: 0,
+        };
+        assert_eq!(subnet("192.0.2.77", 20).encode().unwrap(), vec![0, 1, 20, 0, 192, 0, 0]);
+        assert_eq!(subnet("2001:db8::ff", 33).encode().unwrap(), vec![0, 2, 33, 0, 0x20, 0x01, 0x0d, 0xb8, 0]);
+        assert_eq!(subnet("192.0.2.77", 0).encode().unwrap(), vec![0, 1, 0, 0]);
+        assert_eq!(subnet("192.0.2.77", 32).encode().unwrap(), vec![0, 1, 32, 0, 192, 0, 2, 77]);
+        assert!(subnet("192.0.2.77", 33).encode().is_err());
+        assert!(subnet("2001:db8::", 129).encode().is_err());
+    }
+
+    #[test]
+    fn edns_keeps_malformed_options_as_unknown() {
+        let mut record = Edns::new().to_record().unwrap();
+        record.rdata = vec![0, 11, 0, 1, 7, 0, 10, 0, 3, 1, 2, 3];
+        let edns = Edns::from_record(&record).unwrap();
+        assert_eq!(
+            edns.options,
+            vec![
+                EdnsOption::Unknown { code: 11, data: vec![7] },
+                EdnsOption::Unknown { code: 10, data: vec![1, 2, 3] },
+            ]
+        );
+        assert_eq!(edns.to_record().unwrap(), record);
+
+        record.rdata.pop();
+        assert!(Edns::from_record(&record).is_err());
+    }
+}
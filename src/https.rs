@@ -0,0 +1,462 @@
+use std::{io, net::SocketAddr, panic, thread, time::Duration};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use reqwest::{blocking, header, Url};
+
+use crate::{dns_error::DnsError, transport::Pool, Message, Transport};
+
+/// The media type of DNS messages in HTTP (RFC 8484 6).
+const DNS_MESSAGE: &str = "application/dns-message";
+
+/// The HTTP method a [`DohClient`] sends queries with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HttpMethod {
+    /// GET with the query in the base64url encoded `dns` parameter, which
+    /// caches along the way can answer.
+    Get,
+    /// POST with the query as the request body.
+    Post,
+}
+
+/// The HTTP version a [`DohClient`] speaks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HttpVersion {
+    /// HTTP/2 if the server offers it in the TLS handshake, else HTTP/1.1.
+    Auto,
+    /// HTTP/1.1 only.
+    Http1,
+    /// HTTP/2 only, also without TLS (prior knowledge).
+    Http2,
+}
+
+/// The settings of DNS over HTTPS (RFC 8484).
+///
+/// The URI template names the `dns` variable like `{?dns}`, it is expanded
+/// for GET and removed for POST.  A template without the variable gets the
+/// parameter appended for GET.
+///
+/// # Example
+///
+/// ```
+/// use dns::{DohConfig, HttpMethod};
+///
+/// let mut doh = DohConfig::new("https://dns.example/dns-query{?dns}").unwrap();
+/// doh.set_method(HttpMethod::Post);
+/// assert_eq!(doh.url(&[1, 2, 3]), "https://dns.example/dns-query");
+///
+/// doh.set_method(HttpMethod::Get);
+/// assert_eq!(doh.url(&[1, 2, 3]), "https://dns.example/dns-query?dns=AQID");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DohConfig {
+    template: String,
+    method: HttpMethod,
+    version: HttpVersion,
+}
+impl DohConfig {
+    /// # Creates a new DohConfig
+    ///
+    /// # Errors
+    ///
+    /// returns [`DnsError::HttpsError`] if the template is not a URL once
+    /// the variable is removed.
+    ///
+    /// # Note
+    ///
+    /// This method creates a new DohConfig with the following default values:
+    ///
+    /// - method: HttpMethod::Get
+    /// - version: HttpVersion::Auto
+    pub fn new(template: &str) -> Result<DohConfig, DnsError> {
+        let config = DohConfig {
+            template: template.to_string(),
+            method: HttpMethod::Get,
+            version: HttpVersion::Auto,
+        };
+        config.base()?;
+        Ok(config)
+    }
+
+    /// # Returns the URI template
+    pub fn template(&self) -> &str {
+        &self.template
+    }
+
+    /// # Sets the HTTP method
+    pub fn set_method(&mut self, method: HttpMethod) {
+        self.method = method;
+    }
+
+    /// # Returns the HTTP method
+    pub fn method(&self) -> HttpMethod {
+        self.method
+    }
+
+    /// # Sets the HTTP version
+    pub fn set_version(&mut self, version: HttpVersion) {
+        self.version = version;
+    }
+
+    /// # Returns the HTTP version
+    pub fn version(&self) -> HttpVersion {
+        self.version
+    }
+
+    /// # Returns the URL a query is sent to
+    ///
+    /// # Arguments
+    ///
+    /// takes the query in wire format, it is only used for GET.
+    pub fn url(&self, query: &[u8]) -> String {
+        let value = URL_SAFE_NO_PAD.encode(query);
+        let (before, expression, after) = self.split();
+        match (self.method, expression) {
+            (HttpMethod::Post, _) => format!("{before}{after}"),
+            (HttpMethod::Get, Some("{?dns}")) => format!("{before}?dns={value}{after}"),
+            (HttpMethod::Get, Some("{&dns}")) => format!("{before}&dns={value}{after}"),
+            (HttpMethod::Get, Some(_)) => format!("{before}{value}{after}"),
+            (HttpMethod::Get, None) if before.contains('?') => format!("{before}&dns={value}"),
+            (HttpMethod::Get, None) => format!("{before}?dns={value}"),
+        }
+    }
+
+    /// Splits the template around the expression of the `dns` variable.
+    fn split(&self) -> (&str, Option<&str>, &str) {
+        for expression in ["{?dns}", "{&dns}", "{dns}"] {
+            if let Some((before, after)) = self.template.split_once(expression) {
+                return (before, Some(expression), after);
+            }
+        }
+        (&self.template, None, "")
+    }
+
+    /// Returns the template as a URL, without the variable.
+    fn base(&self) -> Result<Url, DnsError> {
+        let (before, _, after) = self.split();
+        Url::parse(&format!("{before}{after}")).map_err(https_error)
+    }
+}
+
+fn https_error<E: Into<Box<dyn std::error::Error + Send + Sync>>>(e: E) -> DnsError {
+    DnsError::HttpsError(io::Error::other(e))
+}
+
+/// Sends queries with DNS over HTTPS (RFC 8484).
+///
+/// The query goes out with ID 0, which makes GET requests cacheable, and
+/// the reply gets the ID of the query back.
+///
+/// The HTTP client is a blocking one, which must not run on a tokio
+/// runtime: called from async code the client is built and the exchange
+/// runs on a thread of its own, the calling worker thread blocks while it
+/// waits for the reply.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use dns::{DohClient, DohConfig, Message};
+///
+/// let doh = DohConfig::new("https://cloudflare-dns.com/dns-query{?dns}").unwrap();
+/// let client = DohClient::new(doh, Duration::from_secs(5)).unwrap();
+///
+/// let mut message = Message::new();
+/// message.set_questions(vec!["example.com".to_string()]).unwrap();
+/// let response = client.send(&message).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct DohClient {
+    config: DohConfig,
+    http: blocking::Client,
+    timeout: Duration,
+}
+impl DohClient {
+    /// # Creates a new DohClient
+    pub fn new(config: DohConfig, timeout: Duration) -> Result<DohClient, DnsError> {
+        DohClient::build(config, None, timeout)
+    }
+
+    /// Creates a DohClient that connects to `server` instead of resolving
+    /// the host of the template.  The port of `server` is not used, it
+    /// comes from the template.
+    pub(crate) fn with_server(config: DohConfig, server: SocketAddr, timeout: Duration) -> Result<DohClient, DnsError> {
+        DohClient::build(config, Some(server), timeout)
+    }
+
+    fn build(config: DohConfig, server: Option<SocketAddr>, timeout: Duration) -> Result<DohClient, DnsError> {
+        let mut builder = blocking::Client::builder().timeout(timeout);
+        builder = match config.version {
+            HttpVersion::Auto => builder,
+            HttpVersion::Http1 => builder.http1_only(),
+            HttpVersion::Http2 => builder.http2_prior_knowledge(),
+        };
+        if let (Some(server), Some(host)) = (server, config.base()?.host_str()) {
+            builder = builder.resolve(host, SocketAddr::new(server.ip(), 0));
+        }
+        Ok(DohClient {
+            http: off_runtime(|| builder.build()).map_err(https_error)?,
+            config,
+            timeout,
+        })
+    }
+
+    /// # Returns the settings of this client
+    pub fn config(&self) -> &DohConfig {
+        &self.config
+    }
+
+    /// # Sends the message and waits for the reply
+    ///
+    /// # Errors
+    ///
+    /// returns [`DnsError::HttpStatus`] if the server did not answer with a
+    /// success status and a DNS message, and [`DnsError::MismatchedResponse`]
+    /// if that message does not answer the query.
+    pub fn send(&self, message: &Message) -> Result<Message, DnsError> {
+        self.exchange(message, self.timeout)
+    }
+
+    /// Sends the message, waiting up to `timeout`.
+    pub(crate) fn exchange(&self, message: &Message, timeout: Duration) -> Result<Message, DnsError> {
+        off_runtime(|| self.exchange_blocking(message, timeout))
+    }
+
+    fn exchange_blocking(&self, message: &Message, timeout: Duration) -> Result<Message, DnsError> {
+        let mut query = message.clone();
+        query.header.set_id(0);
+        let data = query.get_packet()?;
+        let url = self.config.url(&data);
+        let request = match self.config.method {
+            HttpMethod::Get => self.http.get(url),
+            HttpMethod::Post => self.http.post(url).header(header::CONTENT_TYPE, DNS_MESSAGE).body(data),
+        };
+        let response = request
+            .header(header::ACCEPT, DNS_MESSAGE)
+            .timeout(timeout)
+            .send()
+            .map_err(|e| match e.is_timeout() {
+                true => DnsError::HttpsError(io::Error::new(io::ErrorKind::TimedOut, e)),
+                false => https_error(e),
+            })?;
+        let status = response.status();
+        let is_dns = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .is_some_and(|v| v.as_bytes().starts_with(DNS_MESSAGE.as_bytes()));
+        if !status.is_success() || !is_dns {
+            return Err(DnsError::HttpStatus(status.as_u16()));
+        }
+        let body = response.bytes().map_err(https_error)?;
+        let mut reply = Message::parse(&body)?;
+        if !reply.is_response_to(&query) {
+            return Err(DnsError::MismatchedResponse);
+        }
+        reply.header.set_id(message.header.id);
+        Ok(reply)
+    }
+}
+
+/// Runs `f` on a thread of its own if called on a tokio runtime, where the
+/// blocking HTTP client panics.
+fn off_runtime<T: Send>(f: impl FnOnce() -> T + Send) -> T {
+    if tokio::runtime::Handle::try_current().is_err() {
+        return f();
+    }
+    thread::scope(|scope| scope.spawn(f).join()).unwrap_or_else(|e| panic::resume_unwind(e))
+}
+
+/// Sends queries over DNS over HTTPS as set by the [`DohConfig`], one
+/// [`DohClient`] per server.
+///
+/// The host of the URI template is reached at the address of the server the
+/// query is for, the port comes from the template.
+#[derive(Debug)]
+pub struct HttpsTransport {
+    config: DohConfig,
+    clients: Pool<DohClient>,
+}
+impl HttpsTransport {
+    /// # Creates a new HttpsTransport
+    pub fn new(config: DohConfig) -> HttpsTransport {
+        HttpsTransport {
+            config,
+            clients: Pool::new(),
+        }
+    }
+}
+impl Transport for HttpsTransport {
+    fn send(&self, server: SocketAddr, query: &Message, timeout: Duration) -> Result<Message, DnsError> {
+        let client = self.clients.get(server, || DohClient::with_server(self.config.clone(), server, timeout))?;
+        match client.exchange(query, timeout) {
+            Err(DnsError::HttpsError(e)) if e.kind() == io::ErrorKind::TimedOut => Err(DnsError::Timeout { server, timeout }),
+            result => result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Client, ClientConfig, Protocol, RData, Resource};
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    fn query(name: &str) -> Message {
+        let mut message = Message::new();
+        message.set_questions(vec![name.to_string()]).unwrap();
+        message
+    }
+
+    fn answer(query: &[u8]) -> Vec<u8> {
+        let mut response = Message::parse(query).unwrap();
+        response.header.set_qr(true);
+        let name = response.question[0].qname.clone();
+        response.answer.push(Resource::new(name, 60, RData::A([192, 0, 2, 1].into())).unwrap());
+        response.get_packet().unwrap()
+    }
+
+    /// Answers one HTTP/1.1 request, returning its request line.
+    fn http1_server() -> (SocketAddr, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                if let Some(v) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    length = v.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            if body.is_empty() {
+                let (_, value) = request_line.split_once("dns=").unwrap();
+                body = URL_SAFE_NO_PAD.decode(value.split(' ').next().unwrap()).unwrap();
+            }
+            let reply = answer(&body);
+            let mut stream = reader.into_inner();
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: {DNS_MESSAGE}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                reply.len()
+            )
+            .unwrap();
+            stream.write_all(&reply).unwrap();
+            request_line
+        });
+        (addr, handle)
+    }
+
+    /// Answers one HTTP/2 POST request without TLS.
+    fn http2_server() -> (SocketAddr, thread::JoinHandle<String>) {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+            runtime.block_on(async move {
+                listener.set_nonblocking(true).unwrap();
+                let listener = tokio::net::TcpListener::from_std(listener).unwrap();
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut connection = h2::server::handshake(stream).await.unwrap();
+                let (methods, method) = std::sync::mpsc::channel();
+                // accepting drives the connection, the request is handled
+                // on its own task
+                while let Some(request) = connection.accept().await {
+                    let (request, mut respond) = request.unwrap();
+                    let methods = methods.clone();
+                    tokio::spawn(async move {
+                        methods.send(request.method().to_string()).unwrap();
+                        let mut body = request.into_body();
+                        let mut data = vec![];
+                        while let Some(chunk) = body.data().await {
+                            data.extend_from_slice(&chunk.unwrap());
+                        }
+                        let response = http::Response::builder().header("content-type", DNS_MESSAGE).body(()).unwrap();
+                        let mut send = respond.send_response(response, false).unwrap();
+                        send.send_data(bytes::Bytes::from(answer(&data)), true).unwrap();
+                    });
+                }
+                method.recv().unwrap()
+            })
+        });
+        (addr, handle)
+    }
+
+    #[test]
+    fn doh_url_expands_the_template() {
+        let doh = DohConfig::new("https://dns.example/q{?dns}").unwrap();
+        assert_eq!(doh.url(&[0xfb, 0xff]), "https://dns.example/q?dns=-_8");
+        let doh = DohConfig::new("https://dns.example/q?ct{&dns}").unwrap();
+        assert_eq!(doh.url(&[0]), "https://dns.example/q?ct&dns=AA");
+        let doh = DohConfig::new("https://dns.example/q?ct").unwrap();
+        assert_eq!(doh.url(&[0]), "https://dns.example/q?ct&dns=AA");
+        assert!(DohConfig::new("dns-query{?dns}").is_err());
+    }
+
+    #[test]
+    fn doh_client_sends_get_over_http1() {
+        let (addr, handle) = http1_server();
+        let mut doh = DohConfig::new(&format!("http://{addr}/dns-query{{?dns}}")).unwrap();
+        doh.set_version(HttpVersion::Http1);
+        let client = DohClient::new(doh, Duration::from_secs(5)).unwrap();
+
+        let message = query("example.com");
+        let response = client.send(&message).unwrap();
+        let request_line = handle.join().unwrap();
+
+        assert!(request_line.starts_with("GET /dns-query?dns="));
+        assert!(request_line.ends_with("HTTP/1.1\r\n"));
+        assert_eq!(response.header.id, message.header.id);
+        assert_eq!(response.answer.len(), 1);
+    }
+
+    #[test]
+    fn doh_client_sends_post_over_http2() {
+        let (addr, handle) = http2_server();
+        let mut doh = DohConfig::new(&format!("http://{addr}/dns-query{{?dns}}")).unwrap();
+        doh.set_method(HttpMethod::Post);
+        doh.set_version(HttpVersion::Http2);
+        let client = DohClient::new(doh, Duration::from_secs(5)).unwrap();
+
+        let response = client.send(&query("example.com")).unwrap();
+        drop(client);
+        assert_eq!(handle.join().unwrap(), "POST");
+        assert_eq!(response.answer.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn doh_client_can_be_used_from_async_code() {
+        let (addr, handle) = http1_server();
+        let mut doh = DohConfig::new(&format!("http://{addr}/dns-query{{?dns}}")).unwrap();
+        doh.set_version(HttpVersion::Http1);
+        let client = DohClient::new(doh, Duration::from_secs(5)).unwrap();
+        assert_eq!(client.send(&query("example.com")).unwrap().answer.len(), 1);
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn client_sends_over_https() {
+        let (addr, handle) = http1_server();
+        let mut config = ClientConfig::new();
+        // the host of the template is connected to at the server address
+        config.set_servers(vec![addr]);
+        config.set_protocol(Protocol::Https);
+        config.set_doh(DohConfig::new(&format!("http://dns.test:{}/dns-query", addr.port())).unwrap());
+
+        let response = Client::new(config).send(&query("example.com")).unwrap();
+        handle.join().unwrap();
+        assert_eq!(response.answer.len(), 1);
+    }
+}
@@ -0,0 +1,556 @@
+use std::{
+    fmt,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::Duration,
+};
+
+use crate::{
+    dns_error::DnsError, Chain, DnsClass, Edns, Message, Name, Question, RData, Rcode, RecordType, Resource, Transport,
+    UdpTransport,
+};
+
+/// The root servers with their IPv4 and IPv6 addresses, from the root hints
+/// file of IANA (`named.root`).
+const ROOT_SERVERS: [(&str, Ipv4Addr, Ipv6Addr); 13] = [
+    ("a.root-servers.net", Ipv4Addr::new(198, 41, 0, 4), Ipv6Addr::new(0x2001, 0x503, 0xba3e, 0, 0, 0, 0x2, 0x30)),
+    ("b.root-servers.net", Ipv4Addr::new(170, 247, 170, 2), Ipv6Addr::new(0x2801, 0x1b8, 0x10, 0, 0, 0, 0, 0xb)),
+    ("c.root-servers.net", Ipv4Addr::new(192, 33, 4, 12), Ipv6Addr::new(0x2001, 0x500, 0x2, 0, 0, 0, 0, 0xc)),
+    ("d.root-servers.net", Ipv4Addr::new(199, 7, 91, 13), Ipv6Addr::new(0x2001, 0x500, 0x2d, 0, 0, 0, 0, 0xd)),
+    ("e.root-servers.net", Ipv4Addr::new(192, 203, 230, 10), Ipv6Addr::new(0x2001, 0x500, 0xa8, 0, 0, 0, 0, 0xe)),
+    ("f.root-servers.net", Ipv4Addr::new(192, 5, 5, 241), Ipv6Addr::new(0x2001, 0x500, 0x2f, 0, 0, 0, 0, 0xf)),
+    ("g.root-servers.net", Ipv4Addr::new(192, 112, 36, 4), Ipv6Addr::new(0x2001, 0x500, 0x12, 0, 0, 0, 0, 0xd0d)),
+    ("h.root-servers.net", Ipv4Addr::new(198, 97, 190, 53), Ipv6Addr::new(0x2001, 0x500, 0x1, 0, 0, 0, 0, 0x53)),
+    ("i.root-servers.net", Ipv4Addr::new(192, 36, 148, 17), Ipv6Addr::new(0x2001, 0x7fe, 0, 0, 0, 0, 0, 0x53)),
+    ("j.root-servers.net", Ipv4Addr::new(192, 58, 128, 30), Ipv6Addr::new(0x2001, 0x503, 0xc27, 0, 0, 0, 0x2, 0x30)),
+    ("k.root-servers.net", Ipv4Addr::new(193, 0, 14, 129), Ipv6Addr::new(0x2001, 0x7fd, 0, 0, 0, 0, 0, 0x1)),
+    ("l.root-servers.net", Ipv4Addr::new(199, 7, 83, 42), Ipv6Addr::new(0x2001, 0x500, 0x9f, 0, 0, 0, 0, 0x42)),
+    ("m.root-servers.net", Ipv4Addr::new(202, 12, 27, 33), Ipv6Addr::new(0x2001, 0xdc3, 0, 0, 0, 0, 0, 0x35)),
+];
+
+/// How deep the lookups of name server addresses without glue may nest.
+const MAX_NS_DEPTH: usize = 4;
+
+/// # Returns the addresses of the root servers
+///
+/// # Returns
+///
+/// returns the IPv4 addresses of the 13 root servers followed by their IPv6
+/// addresses, all on port 53.
+///
+/// # Example
+///
+/// ```
+/// let roots = dns::root_hints();
+/// assert_eq!(roots.len(), 26);
+/// assert_eq!(roots[0], "198.41.0.4:53".parse().unwrap());
+/// ```
+pub fn root_hints() -> Vec<SocketAddr> {
+    let v4 = ROOT_SERVERS.iter().map(|(_, ip, _)| IpAddr::V4(*ip));
+    let v6 = ROOT_SERVERS.iter().map(|(_, _, ip)| IpAddr::V6(*ip));
+    v4.chain(v6).map(|ip| SocketAddr::new(ip, 53)).collect()
+}
+
+/// Answers queries without a recursive server, asking the authoritative
+/// servers from the root down.
+///
+/// Each query starts at the root servers and follows the referrals, the NS
+/// records in the authority section, to the servers of the zones below.
+/// Their addresses are taken from the additional section (glue) only when
+/// the names lie within the zone of the server that sent them, the other
+/// names are looked up from the root like any other.  The answer of the
+/// first authoritative server is returned without the records of names
+/// outside its zone, which it has no authority over, so aliases to other
+/// zones are left to the caller, like [`crate::Resolver`] does.
+///
+/// A server that does not answer in time or answers with an error other
+/// than NXDOMAIN is skipped for the next one of the zone.
+///
+/// # Example
+///
+/// ```no_run
+/// use dns::{IterativeResolver, Resolver};
+///
+/// let resolver = Resolver::iterative(IterativeResolver::new());
+/// println!("{:?}", resolver.lookup_ip("example.com").unwrap());
+/// ```
+pub struct IterativeResolver {
+    transport: Box<dyn Transport>,
+    roots: Vec<SocketAddr>,
+    timeout: Duration,
+    max_referrals: usize,
+    max_queries: usize,
+}
+impl IterativeResolver {
+    /// # Creates a new IterativeResolver
+    ///
+    /// # Note
+    ///
+    /// This method creates a new IterativeResolver with the following
+    /// default values:
+    ///
+    /// - transport: UdpTransport   // TCP for truncated replies
+    /// - roots: root_hints()
+    /// - timeout: 2s               // for each server
+    /// - max_referrals: 16         // for each name
+    /// - max_queries: 64           // for each query, with the name servers
+    pub fn new() -> IterativeResolver {
+        IterativeResolver::with_transport(UdpTransport::new())
+    }
+
+    /// # Creates a new IterativeResolver that sends its queries with `transport`
+    pub fn with_transport<T: Transport + 'static>(transport: T) -> IterativeResolver {
+        IterativeResolver {
+            transport: Box::new(transport),
+            roots: root_hints(),
+            timeout: Duration::from_secs(2),
+            max_referrals: 16,
+            max_queries: 64,
+        }
+    }
+
+    /// # Sets the servers every resolution starts with
+    pub fn set_roots(&mut self, roots: Vec<SocketAddr>) {
+        self.roots = roots;
+    }
+
+    /// # Returns the servers every resolution starts with
+    pub fn roots(&self) -> &[SocketAddr] {
+        &self.roots
+    }
+
+    /// # Sets how long to wait for each server
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// # Returns how long to wait for each server
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// # Sets how many referrals are followed for one name
+    pub fn set_max_referrals(&mut self, max_referrals: usize) {
+        self.max_referrals = max_referrals;
+    }
+
+    /// # Returns how many referrals are followed for one name
+    pub fn max_referrals(&self) -> usize {
+        self.max_referrals
+    }
+
+    /// # Sets how many queries one resolution may send
+    ///
+    /// The queries for the addresses of name servers count as well.
+    pub fn set_max_queries(&mut self, max_queries: usize) {
+        self.max_queries = max_queries;
+    }
+
+    /// # Returns how many queries one resolution may send
+    pub fn max_queries(&self) -> usize {
+        self.max_queries
+    }
+
+    /// # Resolves the first question of the message
+    ///
+    /// # Returns
+    ///
+    /// returns the response of the authoritative server, with the ID of
+    /// `message` and only the records of names within its zone.
+    ///
+    /// # Errors
+    ///
+    /// returns [`DnsError::TooManyReferrals`] or [`DnsError::TooManyQueries`]
+    /// when a limit is reached, [`DnsError::NoNameServers`] if the addresses
+    /// of the servers of a zone could not be found and the error of the last
+    /// server if none of a zone answered, which is
+    /// [`DnsError::InvalidReferral`] for a referral that does not lead closer
+    /// to the name.
+    pub fn send(&self, message: &Message) -> Result<Message, DnsError> {
+        let question = message.question.first().ok_or(DnsError::NoQuestion)?;
+        let mut queries = 0;
+        let mut response = self.resolve(question, &mut queries, 0)?;
+        response.header.set_id(message.header.id);
+        Ok(response)
+    }
+}
+impl IterativeResolver {
+    /// Follows the referrals from the roots to the answer of `question`.
+    fn resolve(&self, question: &Question, queries: &mut usize, depth: usize) -> Result<Message, DnsError> {
+        let mut zone = Name::root();
+        let mut servers = self.roots.clone();
+        for _ in 0..=self.max_referrals {
+            let response = self.ask(&servers, &zone, question, queries)?;
+            let Some((child, names)) = referral(&response) else { return Ok(in_zone(response, &zone)) };
+            servers = glue(&response, &names, &zone);
+            if servers.is_empty() {
+                servers = self.lookup_servers(&names, &child, queries, depth)?;
+            }
+            zone = child;
+        }
+        Err(DnsError::TooManyReferrals(self.max_referrals))
+    }
+
+    /// Asks the servers of `zone` in turn until one answers.  A referral
+    /// that does not lead from `zone` closer to the name is a failure of the
+    /// server like an error response.
+    fn ask(&self, servers: &[SocketAddr], zone: &Name, question: &Question, queries: &mut usize) -> Result<Message, DnsError> {
+        let mut query = Message::new();
+        query.question.push(question.clone());
+        query.header.qdcount = 1;
+        query.set_edns(Edns::new())?;
+        let mut last = None;
+        for server in servers {
+            if *queries >= self.max_queries {
+                return Err(DnsError::TooManyQueries(self.max_queries));
+            }
+            *queries += 1;
+            match self.transport.send(*server, &query, self.timeout) {
+                Ok(response) if matches!(response.rcode(), Rcode::NoError | Rcode::NXDomain) => match referral(&response) {
+                    Some((child, _)) if child == *zone || !child.is_subdomain_of(zone) || !question.qname.is_subdomain_of(&child) => {
+                        last = Some(DnsError::InvalidReferral(child))
+                    }
+                    _ => return Ok(response),
+                },
+                Ok(response) => last = Some(DnsError::ErrorResponse(response.rcode())),
+                Err(DnsError::Timeout { .. }) => {}
+                Err(e) => last = Some(e),
+            }
+        }
+        Err(last.unwrap_or(DnsError::RetriesExhausted { attempts: 1 }))
+    }
+
+    /// Looks up the addresses of the first name server that has any.
+    fn lookup_servers(&self, names: &[Name], zone: &Name, queries: &mut usize, depth: usize) -> Result<Vec<SocketAddr>, DnsError> {
+        if depth < MAX_NS_DEPTH {
+            // names within the zone can not be found without glue
+            for name in names.iter().filter(|name| !name.is_subdomain_of(zone)) {
+                let mut question = Question {
+                    qname: name.clone(),
+                    ..Question::new()
+                };
+                question.set_qtype(RecordType::A);
+                let response = match self.resolve(&question, queries, depth + 1) {
+                    Ok(response) => response,
+                    Err(e @ (DnsError::TooManyQueries(_) | DnsError::TooManyReferrals(_))) => return Err(e),
+                    Err(_) => continue,
+                };
+                let mut chain = Chain::new(name.clone());
+                if chain.follow(&response.answer, RecordType::A, DnsClass::IN).is_err() {
+                    continue;
+                }
+                let servers: Vec<SocketAddr> = chain.records.iter().filter_map(address).collect();
+                if !servers.is_empty() {
+                    return Ok(servers);
+                }
+            }
+        }
+        Err(DnsError::NoNameServers(zone.clone()))
+    }
+}
+impl Default for IterativeResolver {
+    fn default() -> Self {
+        IterativeResolver::new()
+    }
+}
+impl fmt::Debug for IterativeResolver {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("IterativeResolver")
+            .field("roots", &self.roots)
+            .field("timeout", &self.timeout)
+            .field("max_referrals", &self.max_referrals)
+            .field("max_queries", &self.max_queries)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Returns the zone and the name servers a response refers to, if it is a
+/// referral: no answer, no SOA and NS records in the authority section.
+fn referral(response: &Message) -> Option<(Name, Vec<Name>)> {
+    if response.rcode() != Rcode::NoError || !response.answer.is_empty() {
+        return None;
+    }
+    if response.authority.iter().any(|r| r.rtype == RecordType::SOA) {
+        return None;
+    }
+    let zone = response.authority.iter().find(|r| r.rtype == RecordType::NS)?.name.clone();
+    let names = response
+        .authority
+        .iter()
+        .filter(|r| r.rtype == RecordType::NS && r.name == zone)
+        .filter_map(|r| match r.data() {
+            Ok(RData::NS(name)) => Some(name),
+            _ => None,
+        })
+        .collect();
+    Some((zone, names))
+}
+
+/// Drops the records of names outside `zone`, the zone of the server that
+/// sent the response, so that it can not plant records for other zones,
+/// like the target of a CNAME.  The OPT record is kept.
+fn in_zone(mut response: Message, zone: &Name) -> Message {
+    let keep = |r: &Resource| r.rtype == RecordType::OPT || r.name.is_subdomain_of(zone);
+    response.answer.retain(keep);
+    response.authority.retain(keep);
+    response.additional.retain(keep);
+    response.header.ancount = response.answer.len() as u16;
+    response.header.nscount = response.authority.len() as u16;
+    response.header.arcount = response.additional.len() as u16;
+    response
+}
+
+/// Returns the addresses the additional section gives for the name servers
+/// that lie within `zone`, the zone of the server that sent them, IPv4
+/// first.
+fn glue(response: &Message, names: &[Name], zone: &Name) -> Vec<SocketAddr> {
+    let trusted = |r: &&Resource| names.contains(&r.name) && r.name.is_subdomain_of(zone);
+    let mut servers: Vec<SocketAddr> = response.additional.iter().filter(trusted).filter_map(address).collect();
+    servers.sort_by_key(SocketAddr::is_ipv6);
+    servers
+}
+
+/// Returns the server address of an A or AAAA record.
+fn address(record: &Resource) -> Option<SocketAddr> {
+    match record.data() {
+        Ok(RData::A(ip)) => Some(SocketAddr::new(IpAddr::V4(ip), 53)),
+        Ok(RData::AAAA(ip)) => Some(SocketAddr::new(IpAddr::V6(ip), 53)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Resolver, Soa};
+    use std::{collections::HashMap, sync::Mutex};
+
+    /// The address, zone and records of a test server.
+    type Zone = (&'static str, &'static str, Vec<(&'static str, RData)>);
+
+    /// Authoritative servers, each with the records of its zone, including
+    /// the delegations to the zones below and their glue.
+    struct Servers {
+        zones: HashMap<SocketAddr, (Name, Vec<Resource>)>,
+        asked: Mutex<Vec<(SocketAddr, Name)>>,
+    }
+    impl Servers {
+        fn new(zones: Vec<Zone>) -> Servers {
+            let zones = zones
+                .into_iter()
+                .map(|(server, zone, records)| {
+                    let records = records.into_iter().map(|(name, data)| record(name, data)).collect();
+                    (server.parse().unwrap(), (zone.parse().unwrap(), records))
+                })
+                .collect();
+            Servers {
+                zones,
+                asked: Mutex::new(vec![]),
+            }
+        }
+    }
+    impl Transport for Servers {
+        fn send(&self, server: SocketAddr, query: &Message, timeout: Duration) -> Result<Message, DnsError> {
+            let question = &query.question[0];
+            self.asked.lock().unwrap().push((server, question.qname.clone()));
+            let Some((zone, records)) = self.zones.get(&server) else { return Err(DnsError::Timeout { server, timeout }) };
+            let mut response = query.clone();
+            response.header.set_qr(true);
+            response.remove_edns();
+
+            let cut = records
+                .iter()
+                .filter(|r| r.rtype == RecordType::NS && r.name != *zone && question.qname.is_subdomain_of(&r.name))
+                .max_by_key(|r| r.name.label_count());
+            if let Some(cut) = cut {
+                for ns in records.iter().filter(|r| r.rtype == RecordType::NS && r.name == cut.name) {
+                    let RData::NS(target) = ns.data().unwrap() else { unreachable!() };
+                    response.authority.push(ns.clone());
+                    response.additional.extend(records.iter().filter(|r| r.name == target && r.rtype != RecordType::NS).cloned());
+                }
+                return Ok(response);
+            }
+            response.header.set_aa(true);
+            let owned: Vec<&Resource> = records.iter().filter(|r| r.name == question.qname).collect();
+            let mut answer: Vec<&Resource> = owned.iter().copied().filter(|r| r.rtype == question.qtype()).collect();
+            if answer.is_empty() {
+                answer = owned.iter().copied().filter(|r| r.rtype == RecordType::CNAME).collect();
+                // adds what the server has for the target, like real servers do
+                for cname in answer.clone() {
+                    let RData::CNAME(target) = cname.data().unwrap() else { unreachable!() };
+                    answer.extend(records.iter().filter(|r| r.name == target && r.rtype == question.qtype()));
+                }
+            }
+            if answer.is_empty() {
+                if owned.is_empty() {
+                    response.header.set_rcode(Rcode::NXDomain);
+                }
+                let soa = Soa {
+                    mname: zone.clone(),
+                    rname: zone.clone(),
+                    serial: 1,
+                    refresh: 3600,
+                    retry: 600,
+                    expire: 86400,
+                    minimum: 300,
+                };
+                response.authority.push(Resource::new(zone.clone(), 300, RData::SOA(soa)).unwrap());
+            }
+            response.answer.extend(answer.into_iter().cloned());
+            Ok(response)
+        }
+    }
+
+    fn record(name: &str, data: RData) -> Resource {
+        Resource::new(name.parse().unwrap(), 300, data).unwrap()
+    }
+
+    fn ns(name: &str) -> RData {
+        RData::NS(name.parse().unwrap())
+    }
+
+    fn a(ip: [u8; 4]) -> RData {
+        RData::A(ip.into())
+    }
+
+    /// The root delegates com and net with glue, example.com is served by
+    /// a name server in example.net.
+    fn internet() -> Vec<Zone> {
+        vec![
+            (
+                "10.0.0.1:53",
+                ".",
+                vec![
+                    ("com", ns("a.gtld.test")),
+                    ("net", ns("a.gtld.test")),
+                    ("a.gtld.test", a([10, 0, 0, 2])),
+                ],
+            ),
+            (
+                "10.0.0.2:53",
+                "com",
+                vec![
+                    ("com", ns("a.gtld.test")),
+                    ("example.com", ns("ns1.example.net")),
+                    // out of the bailiwick of com, must not be used
+                    ("ns1.example.net", a([10, 6, 6, 6])),
+                    ("net", ns("a.gtld.test")),
+                    ("example.net", ns("ns.example.net")),
+                    ("ns.example.net", a([10, 0, 0, 3])),
+                ],
+            ),
+            (
+                "10.0.0.3:53",
+                "example.net",
+                vec![("ns1.example.net", a([10, 0, 0, 4])), ("ns.example.net", a([10, 0, 0, 3]))],
+            ),
+            (
+                "10.0.0.4:53",
+                "example.com",
+                vec![
+                    ("www.example.com", a([192, 0, 2, 1])),
+                    ("alias.example.com", RData::CNAME("ns1.example.net".parse().unwrap())),
+                    // planted with the alias, example.com has no authority over it
+                    ("ns1.example.net", a([10, 6, 6, 6])),
+                ],
+            ),
+        ]
+    }
+
+    fn iterative(servers: Servers) -> IterativeResolver {
+        let mut resolver = IterativeResolver::with_transport(servers);
+        resolver.set_roots(vec!["10.0.0.1:53".parse().unwrap()]);
+        resolver
+    }
+
+    #[test]
+    fn iterative_resolver_follows_referrals() {
+        let servers = std::sync::Arc::new(Servers::new(internet()));
+        let mut resolver = IterativeResolver::with_transport(servers.clone());
+        resolver.set_roots(vec!["10.0.0.9:53".parse().unwrap(), "10.0.0.1:53".parse().unwrap()]);
+        let resolver = Resolver::iterative(resolver);
+
+        let ips = resolver.lookup_ip("www.example.com").unwrap();
+        assert_eq!(ips, vec!["192.0.2.1".parse::<IpAddr>().unwrap()]);
+        let asked = servers.asked.lock().unwrap();
+        // the unreachable root is skipped, the glue from com for example.net
+        // is ignored and ns1.example.net is looked up from the root
+        assert!(asked.iter().all(|(server, _)| server.ip() != "10.6.6.6".parse::<IpAddr>().unwrap()));
+        assert!(asked.contains(&("10.0.0.3:53".parse().unwrap(), "ns1.example.net".parse().unwrap())));
+        assert_eq!(asked.last().unwrap().0, "10.0.0.4:53".parse().unwrap());
+        drop(asked);
+
+        // an alias into another zone is resolved from the root again
+        let ips = resolver.lookup_ip("alias.example.com").unwrap();
+        assert_eq!(ips, vec!["10.0.0.4".parse::<IpAddr>().unwrap()]);
+        assert!(matches!(resolver.lookup_ip("missing.example.com"), Err(DnsError::NxDomain(_))));
+    }
+
+    #[test]
+    fn iterative_resolver_enforces_limits() {
+        let question = |name: &str| {
+            let mut message = Message::new();
+            message.set_questions(vec![name.to_string()]).unwrap();
+            message
+        };
+
+        let mut resolver = iterative(Servers::new(internet()));
+        resolver.set_max_queries(3);
+        assert!(matches!(resolver.send(&question("www.example.com")), Err(DnsError::TooManyQueries(3))));
+
+        let mut resolver = iterative(Servers::new(internet()));
+        resolver.set_max_referrals(1);
+        assert!(matches!(resolver.send(&question("www.example.com")), Err(DnsError::TooManyReferrals(1))));
+
+        // a server that refers to its own zone, the next one is asked
+        let lame = ("10.0.0.2:53", "org", vec![("com", ns("a.gtld.test")), ("a.gtld.test", a([10, 0, 0, 2]))]);
+        let servers = std::sync::Arc::new(Servers::new(vec![
+            (
+                "10.0.0.1:53",
+                ".",
+                vec![
+                    ("com", ns("a.gtld.test")),
+                    ("com", ns("b.gtld.test")),
+                    ("a.gtld.test", a([10, 0, 0, 2])),
+                    ("b.gtld.test", a([10, 0, 0, 3])),
+                ],
+            ),
+            lame.clone(),
+            ("10.0.0.3:53", "com", vec![("example.com", a([192, 0, 2, 1]))]),
+        ]));
+        let mut resolver = IterativeResolver::with_transport(servers.clone());
+        resolver.set_roots(vec!["10.0.0.1:53".parse().unwrap()]);
+        let response = resolver.send(&question("example.com")).unwrap();
+        assert_eq!(response.answer.len(), 1);
+        let asked: Vec<SocketAddr> = servers.asked.lock().unwrap().iter().map(|(server, _)| *server).collect();
+        assert_eq!(asked, ["10.0.0.1:53", "10.0.0.2:53", "10.0.0.3:53"].map(|s| s.parse().unwrap()));
+        let resolver = iterative(Servers::new(vec![
+            ("10.0.0.1:53", ".", vec![("com", ns("a.gtld.test")), ("a.gtld.test", a([10, 0, 0, 2]))]),
+            lame,
+        ]));
+        assert!(matches!(resolver.send(&question("example.com")), Err(DnsError::InvalidReferral(_))));
+
+        // a name server that is only known by a name within its zone
+        let resolver = iterative(Servers::new(vec![("10.0.0.1:53", ".", vec![("com", ns("ns.com"))])]));
+        assert!(matches!(resolver.send(&question("example.com")), Err(DnsError::NoNameServers(_))));
+    }
+
+    #[test]
+    fn iterative_resolver_drops_records_outside_the_zone() {
+        let servers = std::sync::Arc::new(Servers::new(internet()));
+        let resolver = iterative(Servers::new(internet()));
+        let mut query = Message::new();
+        query.set_questions(vec!["alias.example.com".to_string()]).unwrap();
+        let response = resolver.send(&query).unwrap();
+        // the server of example.com answered with the A record of ns1.example.net
+        assert_eq!(response.answer.len(), 1);
+        assert_eq!(response.answer[0].rtype, RecordType::CNAME);
+        assert_eq!(response.header.ancount, 1);
+
+        // the resolver asks the servers of example.net for the target
+        let mut resolver = IterativeResolver::with_transport(servers.clone());
+        resolver.set_roots(vec!["10.0.0.1:53".parse().unwrap()]);
+        let ips = Resolver::iterative(resolver).lookup_ip("alias.example.com").unwrap();
+        assert_eq!(ips, vec!["10.0.0.4".parse::<IpAddr>().unwrap()]);
+        assert!(servers.asked.lock().unwrap().iter().all(|(server, _)| server.ip() != IpAddr::from([10, 6, 6, 6])));
+    }
+}
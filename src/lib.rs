@@ -4,17 +4,92 @@
 //! Make an dns client that supports all dns options.
 //!
 
-mod dns_error;
+pub mod dns_error;
+#[cfg(feature = "tokio")]
+mod async_client;
+mod cache;
+mod chain;
+mod client;
+mod dns_class;
+mod edns;
+#[cfg(feature = "https")]
+mod https;
+mod iterative;
+mod name;
+mod opcode;
+mod rcode;
+#[cfg(feature = "quic")]
+mod quic;
+mod rdata;
+mod record_type;
+mod resolv_conf;
+mod resolver;
+mod tcp;
+#[cfg(feature = "tls")]
+mod tls;
+mod transport;
+mod wire;
+
+#[cfg(feature = "tokio")]
+pub use async_client::AsyncClient;
+pub use cache::{Cache, Negative};
+pub use chain::{Chain, MAX_CHAIN_LEN};
+pub use client::{Client, ClientConfig, Protocol};
+pub use dns_class::DnsClass;
+pub use edns::{ClientSubnet, Edns, EdnsOption};
+#[cfg(feature = "https")]
+pub use https::{DohClient, DohConfig, HttpMethod, HttpsTransport, HttpVersion};
+pub use iterative::{root_hints, IterativeResolver};
+pub use name::{Labels, Name};
+pub use opcode::Opcode;
+#[cfg(feature = "quic")]
+pub use quic::{QuicClient, QuicTransport, DOQ_PORT};
+pub use rcode::Rcode;
+pub use rdata::{Caa, Hinfo, Mx, Naptr, RData, Soa, Srv};
+pub use record_type::RecordType;
+pub use resolv_conf::{ResolvConf, RESOLV_CONF_PATH};
+pub use resolver::Resolver;
+pub use tcp::TcpClient;
+#[cfg(feature = "tls")]
+pub use tls::{TlsClient, TlsConfig, TlsTransport, DOT_PORT};
+pub use transport::{TcpTransport, Transport, UdpTransport};
 
 use dns_error::DnsError;
+use wire::{Reader, Received, Writer};
+
+use std::{
+    error::Error,
+    fmt,
+    sync::{Arc, OnceLock},
+};
 
-use std::{error::Error, net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket}};
+/// The five parts of a message, used to tell where an error occurred.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    Header,
+    Question,
+    Answer,
+    Authority,
+    Additional,
+}
+impl fmt::Display for Section {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Section::Header => "header",
+            Section::Question => "question",
+            Section::Answer => "answer",
+            Section::Authority => "authority",
+            Section::Additional => "additional",
+        };
+        f.write_str(name)
+    }
+}
 
 /// All communications inside of the domain protocol are carried in a single
 /// format called a message.  The top level format of message is divided
 /// into 5 sections (some of which are empty in certain cases) shown below:
 #[allow(unused)]
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Message {
     /// The header section is always present.  The header includes fields that
     /// specify which of the remaining sections are present, and also specify
@@ -26,12 +101,12 @@ pub struct Message {
     /// query class (QCLASS), and a query domain name (QNAME).
     pub question: Vec<Question>,
     /// The answer section contains RRs that answer the question.
-    answer: Vec<Resource>,
+    pub answer: Vec<Resource>,
     /// the authority section contains RRs that point toward an authoritative name server.
-    authority: Vec<Resource>,
+    pub authority: Vec<Resource>,
     /// the additional records section contains RRs
     /// which relate to the query, but are not strictly answers for the question.
-    additional: Vec<Resource>,
+    pub additional: Vec<Resource>,
 }
 impl Message {
     /// # Creates a new DnsOption
@@ -57,7 +132,11 @@ impl Message {
     ///
     /// # Arguments
     ///
-    /// takes a vector of strings as an argument.
+    /// takes a vector of strings as an argument.  Each string is parsed as a
+    /// name in presentation format (see [`Name`]) and the names are joined
+    /// from left to right into the name of one new question, which asks for
+    /// the A record in class IN until changed with [`Question::set_qtype()`]
+    /// and [`Question::set_qclass()`].
     ///
     /// # Example
     ///
@@ -66,200 +145,340 @@ impl Message {
     ///
     /// let mut question = Message::new();
     ///
-    /// question.set_questions(vec!["www.google.com".to_string()]);
+    /// question.set_questions(vec!["www.google.com".to_string()]).unwrap();
+    /// assert_eq!(question.question[0].qname.to_string(), "www.google.com");
+    ///
+    /// assert!(question.set_questions(vec!["bad..name".to_string()]).is_err());
     /// ```
     ///
     /// # Note
     ///
-    /// The domain name is represented as a sequence of labels, where
-    /// each label consists of a length octet followed by that
-    /// number of octets.  The domain name terminates with the
-    /// zero length octet for the null label of the root.  Note
-    /// that this field may be an odd number of octets; no
-    /// padding is used.
+    /// Labels longer than 63 octets, names longer than 255 octets and
+    /// non-ASCII input are rejected.
     pub fn set_questions(&mut self, questions: Vec<String>) -> Result<(), Box<dyn Error>>{
-        let mut res = vec![];
-        for label in questions {
-            let mut parts: Vec<&str> = label.split('.').collect();
-            for i in &mut parts {
-                *i = i.trim();
-            }
-            let mut new_parts = vec![];
-            for part in parts {
-                new_parts.push(part.len() as u8);
-                for c in part.chars() {
-                    new_parts.push(c as u8);
-                }
-            }
-            res.append(&mut new_parts);
+        let mut qname = Name::root();
+        for part in questions.iter().rev() {
+            let part: Name = part.trim().parse()?;
+            qname = part.join(&qname)?;
         }
-        res.push(0);
         self.header.qdcount += 1;
         self.question.push(Question {
-            qname: res,
-            qtype: 0,
-            qclass: 0,
+            qname,
+            ..Question::new()
         });
         Ok(())
     }
 
-    /// # creates a message from a vector of bytes
+    /// # Parses a message from its wire format
+    ///
     /// # Arguments
-    /// takes a vector of bytes as an argument.
-    pub fn from(vec: Vec<u8>) -> Message {
-        let mut header = Header::new();
-        header.id = u16::from_be_bytes([vec[0], vec[1]]);
-        header.flags = u16::from_be_bytes([vec[2], vec[3]]);
-        header.qdcount = u16::from_be_bytes([vec[4], vec[5]]);
-        header.ancount = u16::from_be_bytes([vec[6], vec[7]]);
-        header.nscount = u16::from_be_bytes([vec[8], vec[9]]);
-        header.arcount = u16::from_be_bytes([vec[10], vec[11]]);
+    ///
+    /// takes the received bytes as an argument.
+    ///
+    /// # Returns
+    ///
+    /// returns the Message or a [`DnsError`] describing where the input was
+    /// malformed. Every length, count and offset is checked, so truncated or
+    /// hostile input never panics.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use dns::Message;
+    ///
+    /// let bytes = [0x12, 0x34, 0x81, 0x80, 0, 0, 0, 0, 0, 0, 0, 0];
+    /// let message = Message::parse(&bytes).unwrap();
+    /// assert_eq!(message.header.id, 0x1234);
+    ///
+    /// assert!(Message::parse(&bytes[..5]).is_err());
+    /// ```
+    pub fn parse(buf: &[u8]) -> Result<Message, DnsError> {
+        let mut reader = Reader::new(buf);
+        let header = Header::read(&mut reader)?;
 
+        reader.set_section(Section::Question);
         let mut question = vec![];
-        let mut i = 12;
         for _ in 0..header.qdcount {
-            let mut name = vec![];
-
-            while vec[i] != 0 {
-                name.push(vec[i]);
-                i += 1;
-            }
-            name.push(0);
-            i += 1;
-            let qtype = u16::from_be_bytes([vec[i], vec[i + 1]]);
-            i += 2;
-            let qclass = u16::from_be_bytes([vec[i], vec[i + 1]]);
-            i += 2;
-            question.push(Question { qname: name, qtype, qclass });
+            question.push(Question::read(&mut reader)?);
         }
 
+        reader.set_section(Section::Answer);
         let mut answer = vec![];
         for _ in 0..header.ancount {
-            let (new_i, res) = Message::get_resource(vec.clone(), &mut i);
-            i = new_i;
-            answer.push(res);
+            answer.push(Resource::read(&mut reader)?);
         }
 
+        reader.set_section(Section::Authority);
         let mut authority = vec![];
         for _ in 0..header.nscount {
-            let (new_i, res) = Message::get_resource(vec.clone(), &mut i);
-            i = new_i;
-            authority.push(res);
+            authority.push(Resource::read(&mut reader)?);
         }
 
+        reader.set_section(Section::Additional);
         let mut additional = vec![];
         for _ in 0..header.arcount {
-            let (new_i, res) = Message::get_resource(vec.clone(), &mut i);
-            i = new_i;
-            additional.push(res);
+            additional.push(Resource::read(&mut reader)?);
         }
 
-
-        Message {
+        Ok(Message {
             header,
             question,
             answer,
             authority,
             additional,
-        }
+        })
     }
 
-    pub fn get_packet(&self) -> Vec<u8> {
-        let mut res = vec![];
-        res.extend_from_slice(&self.header.id.to_be_bytes());
-        res.extend_from_slice(&self.header.flags.to_be_bytes());
-        res.extend_from_slice(&self.header.qdcount.to_be_bytes());
-        res.extend_from_slice(&self.header.ancount.to_be_bytes());
-        res.extend_from_slice(&self.header.nscount.to_be_bytes());
-        res.extend_from_slice(&self.header.arcount.to_be_bytes());
-        for i in 0..self.header.qdcount {
-            res.append(&mut self.question[i as usize].qname.clone());
-            res.extend_from_slice(&self.question[i as usize].qtype.to_be_bytes());
-            res.extend_from_slice(&self.question[i as usize].qclass.to_be_bytes());
+    /// # Returns the EDNS parameters
+    ///
+    /// # Returns
+    ///
+    /// returns the parameters of the OPT record in the additional section,
+    /// or `None` if the message has none.  More than one OPT record is an
+    /// error (RFC 6891 section 6.1.1).
+    pub fn edns(&self) -> Result<Option<Edns>, DnsError> {
+        let mut opt = self.additional.iter().filter(|r| r.rtype == RecordType::OPT);
+        let edns = opt.next().map(Edns::from_record).transpose()?;
+        if opt.next().is_some() {
+            return Err(DnsError::DuplicateOpt);
         }
-        res
+        Ok(edns)
     }
 
-    /// # Sends the message
-    /// # Returns
-    /// returns a Result with a Message or a Box\<dyn Error\>
+    /// # Sets the EDNS parameters
+    ///
+    /// Replaces any OPT record in the additional section with one built
+    /// from `edns`.
+    ///
     /// # Example
+    ///
     /// ```
-    /// use dns::Message;
-    /// 
+    /// use dns::{Edns, Message};
+    ///
     /// let mut message = Message::new();
-    /// 
-    /// message.set_questions(vec!["www.google.com".to_string()]);
-    /// 
-    /// let res = message.send();
+    /// message.set_edns(Edns::new()).unwrap();
+    /// assert_eq!(message.edns().unwrap().unwrap().udp_payload_size, 1232);
     /// ```
-    pub fn send(&self) -> Result<Message, Box<dyn Error>> {
-        let dns_server: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)), 53);
+    pub fn set_edns(&mut self, edns: Edns) -> Result<(), DnsError> {
+        let record = edns.to_record()?;
+        self.remove_edns();
+        self.additional.push(record);
+        self.header.arcount = self.additional.len() as u16;
+        Ok(())
+    }
 
+    /// # Removes the OPT record from the additional section
+    pub fn remove_edns(&mut self) {
+        self.additional.retain(|r| r.rtype != RecordType::OPT);
+        self.header.arcount = self.additional.len() as u16;
+    }
 
-        let data = self.get_packet();
+    /// # Returns the full response code
+    ///
+    /// Combines the four bits in the header with the upper eight bits from
+    /// the OPT record, if there is a valid one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use dns::{Edns, Message, Rcode};
+    ///
+    /// let mut message = Message::new();
+    /// let mut edns = Edns::new();
+    /// edns.extended_rcode = Rcode::BadCookie.high();
+    /// message.set_edns(edns).unwrap();
+    /// message.header.set_rcode(Rcode::BadCookie);
+    /// assert_eq!(message.rcode(), Rcode::BadCookie);
+    /// ```
+    pub fn rcode(&self) -> Rcode {
+        let high = match self.edns() {
+            Ok(Some(edns)) => edns.extended_rcode,
+            _ => 0,
+        };
+        Rcode::from_parts(self.header.rcode().low(), high)
+    }
 
-        let socket = match UdpSocket::bind("0.0.0.0:0") {
-            Ok(val) => val,
-            Err(e) => dns_error::DnsError::UdpSocketError(e),
+    /// # Returns true if this message answers `query`
+    ///
+    /// The message must be a response with the ID of the query and repeat
+    /// its question section, with names compared case-insensitively.  A
+    /// FORMERR response may leave the question section out, as servers that
+    /// could not parse the query have nothing to repeat.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use dns::Message;
+    ///
+    /// let mut query = Message::new();
+    /// query.set_questions(vec!["example.com".to_string()]).unwrap();
+    ///
+    /// let mut response = query.clone();
+    /// assert!(!response.is_response_to(&query));
+    /// response.header.set_qr(true);
+    /// assert!(response.is_response_to(&query));
+    /// response.header.set_id(query.header.id.wrapping_add(1));
+    /// assert!(!response.is_response_to(&query));
+    /// ```
+    pub fn is_response_to(&self, query: &Message) -> bool {
+        if !self.header.qr() || self.header.id != query.header.id {
+            return false;
         }
-        socket
-            .send_to(&data, dns_server)?;
-
-        let mut buf = [0; 8192];
-        let (amt, _) = socket
-            .recv_from(&mut buf)?;
-
-        let res = Message::from(buf[..amt+5].to_vec());
+        self.question == query.question || (self.question.is_empty() && self.header.rcode() == Rcode::FormErr)
+    }
 
-        Ok(res)
+    /// # Follows the aliases of the first question through the answer section
+    ///
+    /// # Returns
+    ///
+    /// returns the CNAME chain, with DNAME records applied, and the records
+    /// at its end (see [`Chain::follow()`]).  If the chain leaves the answer
+    /// section, [`Chain::target`] is the name to ask for next.
+    ///
+    /// # Errors
+    ///
+    /// returns [`DnsError::AliasLoop`] or [`DnsError::ChainTooLong`] for a
+    /// chain that does not end, and [`DnsError::NoQuestion`] for a message
+    /// without a question.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use dns::{Message, RData, Resource};
+    ///
+    /// let mut response = Message::new();
+    /// response.set_questions(vec!["www.example.com".to_string()]).unwrap();
+    /// response.answer.push(Resource::new("www.example.com".parse().unwrap(), 300, RData::CNAME("example.com".parse().unwrap())).unwrap());
+    /// response.answer.push(Resource::new("example.com".parse().unwrap(), 300, RData::A([192, 0, 2, 1].into())).unwrap());
+    ///
+    /// let chain = response.chain().unwrap();
+    /// assert_eq!(chain.target.to_string(), "example.com");
+    /// assert!(chain.is_complete());
+    /// ```
+    pub fn chain(&self) -> Result<Chain, DnsError> {
+        let question = self.question.first().ok_or(DnsError::NoQuestion)?;
+        let mut chain = Chain::new(question.qname.clone());
+        chain.follow(&self.answer, question.qtype, question.qclass)?;
+        Ok(chain)
     }
 
-    /// # Creates a new Resource
-    /// # Arguments
-    /// takes a vector of bytes and a mutable reference to a usize.
+    /// # Encodes the message with name compression
+    ///
     /// # Returns
-    /// returns a tuple with the usize and a Resource.
-    fn get_resource(vec: Vec<u8>, i: &mut usize) -> (usize, Resource ){
-        let mut name = vec![];
-        let compressed = vec[*i] & 0b1100_0000;
-        if compressed == 0b1100_0000 {
-            let mut offset = u16::from_be_bytes([vec[*i], vec[*i + 1]]) & 0b0011_1111;
-            *i += 2;
-            while vec[offset as usize] != 0 {
-                name.push(vec[offset as usize]);
-                offset += 1;
-            }
-        }else {
-            while vec[*i] != 0 {
-                name.push(vec[*i]);
-                *i += 1;
-            }
-            *i += 1;
+    ///
+    /// returns the wire format of all five sections.  Repeated name suffixes
+    /// in owner names, questions and the RDATA of the RFC 1035 record types
+    /// are replaced with compression pointers, which keeps responses small
+    /// enough for the 512 byte UDP limit.
+    ///
+    /// # Note
+    ///
+    /// The header counts are taken from the length of each section, not from
+    /// the count fields of [`Header`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use dns::Message;
+    ///
+    /// let mut message = Message::new();
+    /// message.set_questions(vec!["www.google.com".to_string()]).unwrap();
+    ///
+    /// let bytes = message.encode().unwrap();
+    /// assert_eq!(Message::parse(&bytes).unwrap().question[0].qname, message.question[0].qname);
+    /// ```
+    pub fn encode(&self) -> Result<Vec<u8>, DnsError> {
+        let mut writer = Writer::new(true);
+        self.write(&mut writer)?;
+        Ok(writer.finish())
+    }
+
+    fn write(&self, writer: &mut Writer) -> Result<(), DnsError> {
+        let count = |len: usize, section| u16::try_from(len).map_err(|_| DnsError::TooManyRecords(section));
+        writer.u16(self.header.id);
+        writer.u16(self.header.flags);
+        writer.u16(count(self.question.len(), Section::Question)?);
+        writer.u16(count(self.answer.len(), Section::Answer)?);
+        writer.u16(count(self.authority.len(), Section::Authority)?);
+        writer.u16(count(self.additional.len(), Section::Additional)?);
+        for question in &self.question {
+            question.write(writer);
         }
-        name.push(0);
-        let rtype = u16::from_be_bytes([vec[*i], vec[*i + 1]]);
-        *i += 2;
-        let rclass = u16::from_be_bytes([vec[*i], vec[*i + 1]]);
-        *i += 2;
-        let ttl = u32::from_be_bytes([vec[*i], vec[*i + 1], vec[*i + 2], vec[*i + 3]]);
-        *i += 4;
-        let rdlength = u16::from_be_bytes([vec[*i], vec[*i + 1]]);
-        *i += 2;
-        let mut rdata = vec![];
-        for _ in 0..rdlength {
-            rdata.push(vec[*i]);
-            *i += 1;
+        for (section, records) in [
+            (Section::Answer, &self.answer),
+            (Section::Authority, &self.authority),
+            (Section::Additional, &self.additional),
+        ] {
+            for record in records {
+                record.write(writer, section)?;
+            }
         }
-        (   *i,
-            Resource {
-            name,
-            rtype,
-            rclass,
-            ttl,
-            rdlength,
-            rdata,
-        })
+        Ok(())
+    }
+
+    /// # Encodes the message without name compression
+    ///
+    /// # Returns
+    ///
+    /// returns the wire format of all five sections.  The questions and
+    /// records of a parsed message are written as they were received,
+    /// compression pointers included, so [`Message::parse`] followed by
+    /// get_packet gives back the same bytes.  Questions and records that were
+    /// built or changed, and those behind them whose pointers would no longer
+    /// point to the same names, are written with every name in full.
+    ///
+    /// # Note
+    ///
+    /// [`Message::parse`] of the result always gives back an equal message.
+    /// Use [`Message::encode`] for the smaller, compressed form.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use dns::Message;
+    ///
+    /// let mut message = Message::new();
+    /// message.set_questions(vec!["www.google.com".to_string()]).unwrap();
+    ///
+    /// let bytes = message.get_packet().unwrap();
+    /// assert_eq!(Message::parse(&bytes).unwrap(), message);
+    /// ```
+    pub fn get_packet(&self) -> Result<Vec<u8>, DnsError> {
+        let mut writer = Writer::new(false);
+        self.write(&mut writer)?;
+        Ok(writer.finish())
+    }
+
+    /// # Sends the message
+    /// # Returns
+    /// returns a Result with a Message or a Box\<dyn Error\>
+    /// # Note
+    /// This is a shortcut for [`Client::send()`] with the system resolver
+    /// configuration from `/etc/resolv.conf` (see [`ClientConfig::system()`]);
+    /// build a [`Client`] to choose the upstream servers or the [`Transport`].
+    /// The configuration is read on the first call and the [`Client`] is
+    /// kept for all later ones, so `options rotate` spreads the queries over
+    /// the servers and TCP connections stay open between calls.  The answers
+    /// are kept for their TTL in a [`Cache`] of 10000 RRsets shared by all
+    /// calls.
+    /// # Example
+    /// ```no_run
+    /// use dns::Message;
+    /// 
+    /// let mut message = Message::new();
+    /// 
+    /// message.set_questions(vec!["www.google.com".to_string()]);
+    /// 
+    /// let res = message.send();
+    /// ```
+    pub fn send(&self) -> Result<Message, Box<dyn Error>> {
+        static CLIENT: OnceLock<Client> = OnceLock::new();
+        let client = CLIENT.get_or_init(|| {
+            let mut client = Client::new(ClientConfig::system());
+            client.set_cache(Arc::new(Cache::new(10_000)));
+            client
+        });
+        Ok(client.send(self)?)
     }
 }
 impl Default for Message {
@@ -268,8 +487,19 @@ impl Default for Message {
     }
 }
 
+const QR_BIT: u16 = 0b1000_0000_0000_0000;
+const OPCODE_MASK: u16 = 0b0111_1000_0000_0000;
+const AA_BIT: u16 = 0b0000_0100_0000_0000;
+const TC_BIT: u16 = 0b0000_0010_0000_0000;
+const RD_BIT: u16 = 0b0000_0001_0000_0000;
+const RA_BIT: u16 = 0b0000_0000_1000_0000;
+const Z_BIT: u16 = 0b0000_0000_0100_0000;
+const AD_BIT: u16 = 0b0000_0000_0010_0000;
+const CD_BIT: u16 = 0b0000_0000_0001_0000;
+const RCODE_MASK: u16 = 0b0000_0000_0000_1111;
+
 #[allow(unused)]
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Header {
     /// A 16 bit identifier assigned by the program that
     /// generates any kind of query.
@@ -324,6 +554,18 @@ impl Header {
         }
     }
 
+    /// Reads the fixed 12 byte header.
+    fn read(reader: &mut Reader) -> Result<Header, DnsError> {
+        Ok(Header {
+            id: reader.u16()?,
+            flags: reader.u16()?,
+            qdcount: reader.u16()?,
+            ancount: reader.u16()?,
+            nscount: reader.u16()?,
+            arcount: reader.u16()?,
+        })
+    }
+
     /// # Sets the id
     ///
     /// # Arguments
@@ -349,78 +591,100 @@ impl Header {
     ///
     /// - 1 bit: QR (Query/Response)
     ///
-    ///    A one bit field that specifies whether this message is a query (0),
-    ///    or a response (1).   
-    /// <br />
+    ///   A one bit field that specifies whether this message is a query (0),
+    ///   or a response (1).
     ///
     /// - 4 bits: Opcode
-    ///     A four bit field that specifies kind of query in this
-    ///     message.  This value is set by the originator of a query
-    ///     and copied into the response.  The values are:
-    ///     
-    ///     - `0`:               a standard query (QUERY)
-    ///     
-    ///     - `1`:               an inverse query (IQUERY)
-    ///     
-    ///     - `2`:               a server status request (STATUS)
-    ///     
-    ///     - `3-15`:            reserved for future use
+    ///   A four bit field that specifies kind of query in this
+    ///   message.  This value is set by the originator of a query
+    ///   and copied into the response.  The values are:
+    ///
+    ///   - `0`: a standard query (QUERY)
+    ///
+    ///   - `1`: an inverse query (IQUERY)
+    ///
+    ///   - `2`: a server status request (STATUS)
+    ///
+    ///   - `4`: a zone change notification (NOTIFY)
+    ///
+    ///   - `5`: a dynamic update (UPDATE)
+    ///
+    ///   - `6`: DNS stateful operations (DSO)
+    ///
+    ///   - `3`, `7-15`: unassigned
     ///
     /// DNS Header Flags
     /// [(source)](https://www.rfc-editor.org/rfc/rfc1035.html)
     ///
     /// - 1 bit: AA (Authoritative Answer)
     ///
-    ///     Authoritative Answer - this bit is valid in responses,
-    ///     and specifies that the responding name server is an
-    ///     authority for the domain name in question section.
-    ///     Note that the contents of the answer section may
-    ///     have multiple owner names because of aliases.   
-    ///<br />
+    ///   Authoritative Answer - this bit is valid in responses,
+    ///   and specifies that the responding name server is an
+    ///   authority for the domain name in question section.
+    ///   Note that the contents of the answer section may
+    ///   have multiple owner names because of aliases.
+    ///
     /// - 1 bit: TC (Truncated)
     ///
-    ///     TrunCation - specifies that this message was truncated
-    /// due to length greater than that permitted on the transmission
-    /// channel.  
-    ///<br />
+    ///   TrunCation - specifies that this message was truncated
+    ///   due to length greater than that permitted on the transmission
+    ///   channel.
+    ///
     /// - 1 bit: RD (Recursion Desired)
     ///
-    ///     Recursion Desired - this bit may be set in a query and
-    ///     is copied into the response.  If RD is set, it directs
-    ///     the name server to pursue the query recursively.
-    ///     Recursive query support is optional.  
-    ///<br />
+    ///   Recursion Desired - this bit may be set in a query and
+    ///   is copied into the response.  If RD is set, it directs
+    ///   the name server to pursue the query recursively.
+    ///   Recursive query support is optional.
+    ///
     /// - 1 bit: RA (Recursion Available)
     ///
-    ///     Recursion Available - this be is set or cleared in
-    ///     a response, and denotes whether recursive query
-    ///     support is available in the name server.  
-    ///<br />
-    /// - 3 bits: Z (Reserved)
+    ///   Recursion Available - this be is set or cleared in
+    ///   a response, and denotes whether recursive query
+    ///   support is available in the name server.
+    ///
+    /// - 1 bit: Z (Reserved)
+    ///
+    ///   Reserved for future use.  Must be zero in all
+    ///   queries and responses.
+    ///
+    /// - 1 bit: AD (Authentic Data)
+    ///
+    ///   Set by a security aware resolver when all data in the answer and
+    ///   authority sections has been validated (RFC 4035).
+    ///
+    /// - 1 bit: CD (Checking Disabled)
+    ///
+    ///   Set in a query to disable DNSSEC validation by the resolver
+    ///   (RFC 4035).
     ///
-    ///     Reserved for future use.  Must be zero in all
-    ///     queries and responses.  
-    ///<br />
     /// - 4 bits: RCODE (Response Code)
     ///
-    ///     Response code - this 4 bit field is set as part of responses.  The values have the following interpretation:
+    ///   Response code - this 4 bit field is set as part of responses.  The values have the following interpretation:
+    ///
+    ///   - `0`: No error condition
+    ///
+    ///   - `1`: Format error - The name server was
+    ///     unable to interpret the query.
     ///
-    ///     - `0`:       No error condition
+    ///   - `2`: Server failure - The name server was
+    ///     unable to process this query due to a
+    ///     problem with the name server.
     ///
-    ///     - `1`:      Format error - The name server was
-    ///                 unable to interpret the query.
+    ///   - `3`: Name Error - Meaningful only for responses from an authoritative name server, this code signifies that the domain name referenced in the query does not exist.
     ///
-    ///     - `2`:       Server failure - The name server was
-    ///                 unable to process this query due to a
-    ///                 problem with the name server.
+    ///   - `4`: Not Implemented - The name server does not support the requested kind of query.
     ///
-    ///     - `3`:       Name Error - Meaningful only for responses from an authoritative name server, this code signifies that the domain name referenced in the query does not exist.
+    ///   - `5`: Refused - The name server refuses to perform the specified operation for policy reasons.  For example, a name server may not wish to provide the information to the particular requester, or a name server may not wish to perform a particular operation (e.g., zone transfer) for particular data.
     ///
-    ///     - `4`:       Not Implemented - The name server does not support the requested kind of query.
+    ///   - `6-11`: YXDOMAIN, YXRRSET, NXRRSET, NOTAUTH, NOTZONE and
+    ///     DSOTYPENI, see [`Rcode`].
     ///
-    ///     - `5`:       Refused - The name server refuses to perform the specified operation for policy reasons.  For example, a name server may not wish to provide the information to the particular requester, or a name server may not wish to perform a particular operation (e.g., zone transfer) for particular data.
+    ///   - `12-15`: unassigned.
     ///
-    ///     - `6-15`:    Reserved for future use.
+    /// Unassigned opcodes and response codes as well as a set Z bit are
+    /// rejected.  The individual fields can also be set with the typed
+    /// setters like [`Header::set_rd()`] and [`Header::set_opcode()`].
     ///
     /// # Example
     ///
@@ -431,23 +695,147 @@ impl Header {
     /// options.set_flags(0b0000_0000_0000_0000);
     /// ```
     pub fn set_flags(&mut self, flags: u16) -> Result<(), Box<dyn Error>> {
-        let mut test = flags & 0b0111_1000_0000_0000;
-        test >>= 11;
-        if test > 2 {
-            return Err(Box::new(DnsError::InvalidOpcodeFlag(test as i32)));
+        let opcode = Opcode::from(((flags & OPCODE_MASK) >> 11) as u8);
+        if !opcode.is_assigned() {
+            return Err(Box::new(DnsError::InvalidOpcodeFlag(u8::from(opcode) as i32)));
         }
-        test = flags & 0b0000_0000_0111_0000;
-        test >>= 4;
-        if test != 0 {
-            return Err(Box::new(DnsError::InvalidZFlag(test as i32)));
+        if flags & Z_BIT != 0 {
+            return Err(Box::new(DnsError::InvalidZFlag(1)));
         }
-        let test = flags & 0b0000_0000_0000_1111;
-        if test > 5 {
-            return Err(Box::new(DnsError::InvalidRcodeFlag(test as i32)));
+        let rcode = flags & RCODE_MASK;
+        if rcode > 11 {
+            return Err(Box::new(DnsError::InvalidRcodeFlag(rcode as i32)));
         }
         self.flags = flags;
         Ok(())
     }
+
+    /// # Returns all flags as they appear on the wire
+    pub fn flags(&self) -> u16 {
+        self.flags
+    }
+
+    fn flag(&self, bit: u16) -> bool {
+        self.flags & bit != 0
+    }
+
+    fn set_flag(&mut self, bit: u16, value: bool) {
+        if value {
+            self.flags |= bit;
+        } else {
+            self.flags &= !bit;
+        }
+    }
+
+    /// # Returns true if the message is a response (QR)
+    pub fn qr(&self) -> bool {
+        self.flag(QR_BIT)
+    }
+
+    /// # Marks the message as a response (true) or a query (false)
+    pub fn set_qr(&mut self, qr: bool) {
+        self.set_flag(QR_BIT, qr);
+    }
+
+    /// # Returns the kind of query
+    pub fn opcode(&self) -> Opcode {
+        Opcode::from(((self.flags & OPCODE_MASK) >> 11) as u8)
+    }
+
+    /// # Sets the kind of query
+    pub fn set_opcode(&mut self, opcode: Opcode) {
+        self.flags = (self.flags & !OPCODE_MASK) | (u8::from(opcode) as u16) << 11;
+    }
+
+    /// # Returns the Authoritative Answer bit (AA)
+    pub fn aa(&self) -> bool {
+        self.flag(AA_BIT)
+    }
+
+    /// # Sets the Authoritative Answer bit (AA)
+    pub fn set_aa(&mut self, aa: bool) {
+        self.set_flag(AA_BIT, aa);
+    }
+
+    /// # Returns the TrunCation bit (TC)
+    pub fn tc(&self) -> bool {
+        self.flag(TC_BIT)
+    }
+
+    /// # Sets the TrunCation bit (TC)
+    pub fn set_tc(&mut self, tc: bool) {
+        self.set_flag(TC_BIT, tc);
+    }
+
+    /// # Returns the Recursion Desired bit (RD)
+    pub fn rd(&self) -> bool {
+        self.flag(RD_BIT)
+    }
+
+    /// # Sets the Recursion Desired bit (RD)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use dns::Header;
+    ///
+    /// let mut header = Header::new();
+    /// header.set_rd(true);
+    /// assert!(header.rd());
+    /// assert_eq!(header.flags(), 0b0000_0001_0000_0000);
+    /// ```
+    pub fn set_rd(&mut self, rd: bool) {
+        self.set_flag(RD_BIT, rd);
+    }
+
+    /// # Returns the Recursion Available bit (RA)
+    pub fn ra(&self) -> bool {
+        self.flag(RA_BIT)
+    }
+
+    /// # Sets the Recursion Available bit (RA)
+    pub fn set_ra(&mut self, ra: bool) {
+        self.set_flag(RA_BIT, ra);
+    }
+
+    /// # Returns the Authentic Data bit (AD)
+    pub fn ad(&self) -> bool {
+        self.flag(AD_BIT)
+    }
+
+    /// # Sets the Authentic Data bit (AD)
+    pub fn set_ad(&mut self, ad: bool) {
+        self.set_flag(AD_BIT, ad);
+    }
+
+    /// # Returns the Checking Disabled bit (CD)
+    pub fn cd(&self) -> bool {
+        self.flag(CD_BIT)
+    }
+
+    /// # Sets the Checking Disabled bit (CD)
+    pub fn set_cd(&mut self, cd: bool) {
+        self.set_flag(CD_BIT, cd);
+    }
+
+    /// # Returns the response code from the four header bits
+    ///
+    /// # Note
+    ///
+    /// Extended response codes also need the upper bits from the EDNS OPT
+    /// record, see [`Rcode::from_parts()`].
+    pub fn rcode(&self) -> Rcode {
+        Rcode::from(self.flags & RCODE_MASK)
+    }
+
+    /// # Sets the response code
+    ///
+    /// # Note
+    ///
+    /// Only the low four bits are stored in the header, see [`Rcode::low()`].
+    pub fn set_rcode(&mut self, rcode: Rcode) {
+        self.flags = (self.flags & !RCODE_MASK) | rcode.low() as u16;
+    }
 }
 impl Default for Header {
     fn default() -> Self {
@@ -456,23 +844,20 @@ impl Default for Header {
 }
 
 #[allow(unused)]
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Question {
-    /// a domain name represented as a sequence of labels, where
-    /// each label consists of a length octet followed by that
-    /// number of octets.  The domain name terminates with the
-    /// zero length octet for the null label of the root.  Note
-    /// that this field may be an odd number of octets; no
-    /// padding is used.
-    pub qname: Vec<u8>,
+    /// the domain name being asked for.
+    pub qname: Name,
     ///  a two octet code which specifies the type of the query.
     ///  The values for this field include all codes valid for a
     ///  TYPE field, together with some more general codes which
     ///  can match more than one type of RR.
-    qtype: u16,
+    qtype: RecordType,
     /// a two octet code that specifies the class of the query.
     /// For example, the QCLASS field is IN for the Internet.
-    qclass: u16,
+    qclass: DnsClass,
+    /// the question as it was received, see [`Message::get_packet()`].
+    received: Received,
 }
 impl Question {
     /// # Creates a new Question
@@ -490,46 +875,79 @@ impl Question {
     /// 
     /// This method creates a new Question with the following default values:
     /// 
-    /// - qname: Vec::new()     // Empty
+    /// - qname: Name::root()   // .
     /// - qtype: 1              // A
     /// - qclass: 1             // IN
     pub fn new() -> Question {
         Question {
-            qname: Vec::new(),
-            qtype: 1,
-            qclass: 1,
+            qname: Name::root(),
+            qtype: RecordType::A,
+            qclass: DnsClass::IN,
+            received: Received::default(),
         }
     }
 
-    pub fn set_qtype(&mut self, qtype: u16) -> Result<(), Box<dyn Error>>{
-        match qtype {
-            // valid qtypes
-            // https://en.wikipedia.org/wiki/List_of_DNS_record_types
-            1 | 2 | 5 | 6 | 12 | 13 | 15 | 16 | 17 | 18 | 24| 25 | 28 | 29 | 33 | 35 | 36 | 37 | 39 | 41 | 42 | 43 | 44 | 45 | 46 | 47 | 48 | 49 | 50 | 51 | 52 | 53 | 55 | 59 | 60 | 61 | 62 | 63 | 64 | 65 | 108 | 109 | 249 | 250 | 251 | 252 | 255 | 256 | 257 | 32768 | 32769 => {
-                self.qtype = qtype;
-                Ok(())
-            }
-            _ => Err(Box::new(DnsError::InvalidQType(qtype))),
-        }
+    /// Reads a question entry at the reader's position.
+    fn read(reader: &mut Reader) -> Result<Question, DnsError> {
+        let start = reader.pos();
+        let qname = reader.name()?;
+        let qtype = reader.u16()?.into();
+        let qclass = reader.u16()?.into();
+        Ok(Question {
+            qname,
+            qtype,
+            qclass,
+            received: reader.received(start),
+        })
     }
 
-    pub fn set_qclass(&mut self, qclass: u16) -> Result<(), Box<dyn Error>> {
-        match qclass {
-            // 0	        0x0000	        Reserved	                [RFC6895]
-            // 1	        0x0001	        Internet (IN)	            [RFC1035]
-            // 2	        0x0002	        Unassigned	
-            // 3	        0x0003	        Chaos (CH)	                [D. Moon, "Chaosnet", A.I. Memo 628, Massachusetts Institute of Technology Artificial Intelligence Laboratory, June 1981.]
-            // 4	        0x0004	        Hesiod (HS)	                [Dyer, S., and F. Hsu, "Hesiod", Project Athena Technical Plan - Name Service, April 1987.]
-            // 5-253	    0x0005-0x00FD	Unassigned	
-            // 254	        0x00FE	        QCLASS NONE	                [RFC2136]
-            // 255	        0x00FF	        QCLASS * (ANY)	            [RFC1035]
-            // 256-65279	0x0100-0xFEFF	Unassigned	
-            // 65280-65534	0xFF00-0xFFFE	Reserved for Private Use	[RFC6895]
-            // 65535	    0xFFFF	        Reserved	                [RFC6895]
-            0 | 5..=253 | 256..=65279 | 65535 => return Err(Box::new(DnsError::InvalidQClass(qclass))),
-            _ => self.qclass = qclass,
+    fn write(&self, writer: &mut Writer) {
+        if writer.received(&self.received, |reader| Question::read(reader).is_ok_and(|q| q == *self)) {
+            return;
         }
-        Ok(())
+        writer.name(self.qname.as_wire());
+        writer.u16(self.qtype.into());
+        writer.u16(self.qclass.into());
+    }
+
+    /// # Sets the type of the query
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use dns::{Question, RecordType};
+    ///
+    /// let mut question = Question::new();
+    /// question.set_qtype(RecordType::SRV);
+    /// assert_eq!(question.qtype(), RecordType::SRV);
+    /// ```
+    pub fn set_qtype(&mut self, qtype: RecordType) {
+        self.qtype = qtype;
+    }
+
+    /// # Returns the type of the query
+    pub fn qtype(&self) -> RecordType {
+        self.qtype
+    }
+
+    /// # Sets the class of the query
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use dns::{DnsClass, Question};
+    ///
+    /// let mut question = Question::new();
+    /// question.set_qclass(DnsClass::CH);
+    /// assert_eq!(question.qclass(), DnsClass::CH);
+    /// ```
+    pub fn set_qclass(&mut self, qclass: DnsClass) {
+        self.qclass = qclass;
+    }
+
+    /// # Returns the class of the query
+    pub fn qclass(&self) -> DnsClass {
+        self.qclass
     }
 }
 impl Default for Question {
@@ -542,30 +960,115 @@ impl Default for Question {
 /// format: a variable number of resource records, where the number of
 /// records is specified in the corresponding count field in the header.
 /// Each resource record has the following format:
-#[allow(unused)]
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Resource {
     /// a domain name to which this resource record pertains.
-    name: Vec<u8>,
+    pub name: Name,
     /// two octets containing one of the RR type codes.  This
     /// field specifies the meaning of the data in the RDATA field.
-    rtype: u16,
+    pub rtype: RecordType,
     /// two octets which specify the class of the data in the RDATA field.
-    rclass: u16,
+    pub rclass: DnsClass,
     /// a 32 bit unsigned integer that specifies the time
     /// interval (in seconds) that the resource record may be
     /// cached before it should be discarded.  Zero values are
     /// interpreted to mean that the RR can only be used for the
     /// transaction in progress, and should not be cached.
-    ttl: u32,
-    /// an unsigned 16 bit integer that specifies the length in octets of the RDATA field.
-    rdlength: u16,
+    pub ttl: u32,
     /// a variable length string of octets that describes the
     /// resource.  The format of this information varies
     /// according to the TYPE and CLASS of the resource record.
     /// For example, the if the TYPE is A and the CLASS is IN,
     /// the RDATA field is a 4 octet ARPA Internet address.
-    rdata: Vec<u8>,
+    ///
+    /// Names inside the RDATA are kept uncompressed, see [`Resource::data()`]
+    /// for the decoded form.
+    pub rdata: Vec<u8>,
+    /// the record as it was received, see [`Message::get_packet()`].
+    received: Received,
+}
+impl Resource {
+    /// # Creates a new Resource in class IN
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use dns::{RData, RecordType, Resource};
+    /// use std::net::Ipv4Addr;
+    ///
+    /// let record = Resource::new(
+    ///     "example.com".parse().unwrap(),
+    ///     300,
+    ///     RData::A(Ipv4Addr::new(192, 0, 2, 1)),
+    /// ).unwrap();
+    /// assert_eq!(record.rtype, RecordType::A);
+    /// assert_eq!(record.rdata, vec![192, 0, 2, 1]);
+    /// ```
+    pub fn new(name: Name, ttl: u32, data: RData) -> Result<Resource, DnsError> {
+        Ok(Resource {
+            name,
+            rtype: data.rtype(),
+            rclass: DnsClass::IN,
+            ttl,
+            rdata: data.encode()?,
+            received: Received::default(),
+        })
+    }
+
+    /// # Decodes the RDATA
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use dns::{RData, Resource, Srv};
+    ///
+    /// let srv = RData::SRV(Srv {
+    ///     priority: 0,
+    ///     weight: 5,
+    ///     port: 25565,
+    ///     target: "mc.example.com".parse().unwrap(),
+    /// });
+    /// let record = Resource::new("_minecraft._tcp.example.com".parse().unwrap(), 300, srv.clone()).unwrap();
+    /// assert_eq!(record.data().unwrap(), srv);
+    /// ```
+    pub fn data(&self) -> Result<RData, DnsError> {
+        RData::decode(self.rtype, &self.rdata)
+    }
+
+    /// # Returns the length of the RDATA (RDLENGTH)
+    pub fn rdlength(&self) -> usize {
+        self.rdata.len()
+    }
+
+    /// Reads a resource record at the reader's position.
+    fn read(reader: &mut Reader) -> Result<Resource, DnsError> {
+        let start = reader.pos();
+        let name = reader.name()?;
+        let rtype = RecordType::from(reader.u16()?);
+        let rclass = DnsClass::from(reader.u16()?);
+        let ttl = reader.u32()?;
+        let rdlength = reader.u16()?;
+        let rdata = reader.rdata(rtype, rdlength)?;
+        Ok(Resource {
+            name,
+            rtype,
+            rclass,
+            ttl,
+            rdata,
+            received: reader.received(start),
+        })
+    }
+
+    fn write(&self, writer: &mut Writer, section: Section) -> Result<(), DnsError> {
+        if writer.received(&self.received, |reader| Resource::read(reader).is_ok_and(|r| r == *self)) {
+            return Ok(());
+        }
+        writer.name(self.name.as_wire());
+        writer.u16(self.rtype.into());
+        writer.u16(self.rclass.into());
+        writer.u32(self.ttl);
+        writer.rdata(self.rtype, &self.rdata, section)
+    }
 }
 #[cfg(test)]
 mod tests {
@@ -574,10 +1077,10 @@ mod tests {
     #[test]
     fn message_set_questions() {
         let mut options = Message::new();
-        options.set_questions(vec!["www.google.com".to_string()]);
+        options.set_questions(vec!["www.google.com".to_string()]).unwrap();
         println!("{:#?}", options);
         assert_eq!(
-            options.question[0].qname,
+            options.question[0].qname.as_wire(),
             vec![3, 119, 119, 119, 6, 103, 111, 111, 103, 108, 101, 3, 99, 111, 109, 0]
         )
     }
@@ -602,4 +1105,154 @@ mod tests {
         let mut options = Header::new();
         options.set_flags(0b0000_0000_0000_1111).unwrap();
     }
+
+    /// A response to `example.com A` with one compressed answer.
+    fn example_response() -> Vec<u8> {
+        vec![
+            0x12, 0x34, 0x81, 0x80, 0, 1, 0, 1, 0, 0, 0, 0,
+            7, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 3, b'c', b'o', b'm', 0, 0, 1, 0, 1,
+            0xc0, 12, 0, 1, 0, 1, 0, 0, 0x0e, 0x10, 0, 4, 93, 184, 216, 34,
+        ]
+    }
+
+    #[test]
+    fn message_parse() {
+        let message = Message::parse(&example_response()).unwrap();
+        assert_eq!(message.header.id, 0x1234);
+        assert_eq!(message.question[0].qname, message.answer[0].name);
+        assert_eq!(message.answer[0].ttl, 3600);
+        assert_eq!(message.answer[0].rdata, vec![93, 184, 216, 34]);
+        assert_eq!(message.answer[0].data().unwrap(), RData::A(std::net::Ipv4Addr::new(93, 184, 216, 34)));
+    }
+
+    #[test]
+    fn message_parse_truncated_never_panics() {
+        let bytes = example_response();
+        for len in 0..bytes.len() {
+            assert!(Message::parse(&bytes[..len]).is_err(), "prefix of {len} bytes parsed");
+        }
+    }
+
+    #[test]
+    fn message_parse_reports_offset_and_section() {
+        let mut bytes = example_response();
+        bytes.truncate(bytes.len() - 2);
+        match Message::parse(&bytes) {
+            Err(DnsError::Truncated { offset, section }) => {
+                assert_eq!(offset, bytes.len());
+                assert_eq!(section, Section::Answer);
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+
+        let mut bytes = example_response();
+        bytes[29] = 0b1000_0000;
+        assert!(matches!(
+            Message::parse(&bytes),
+            Err(DnsError::InvalidLabelType { offset: 29, section: Section::Answer })
+        ));
+
+        let mut bytes = example_response();
+        bytes[30] = 29;
+        assert!(matches!(
+            Message::parse(&bytes),
+            Err(DnsError::InvalidPointer { offset: 29, section: Section::Answer })
+        ));
+    }
+
+    #[test]
+    fn message_encode_compresses_names() {
+        let message = Message::parse(&example_response()).unwrap();
+        assert_eq!(message.encode().unwrap(), example_response());
+
+        let mut message = Message::new();
+        message.set_questions(vec!["example.com".to_string()]).unwrap();
+        message.set_questions(vec!["www.example.com".to_string()]).unwrap();
+        let bytes = message.encode().unwrap();
+        // the second name is `www` followed by a pointer to the first one
+        assert_eq!(&bytes[29..35], &[3, b'w', b'w', b'w', 0xc0, 12]);
+        let parsed = Message::parse(&bytes).unwrap();
+        assert_eq!(parsed.question[1].qname, message.question[1].qname);
+    }
+
+    #[test]
+    fn message_get_packet_round_trips() {
+        let mut bytes = vec![0xab, 0xcd, 0x85, 0x00, 0, 1, 0, 1, 0, 1, 0, 1];
+        bytes.extend_from_slice(b"\x07example\x03com\x00\x00\x0f\x00\x01");
+        // answer: MX 10 mail.example.com
+        bytes.extend_from_slice(b"\x07example\x03com\x00\x00\x0f\x00\x01\x00\x00\x01\x2c\x00\x14");
+        bytes.extend_from_slice(b"\x00\x0a\x04mail\x07example\x03com\x00");
+        // authority: NS ns.example.com
+        bytes.extend_from_slice(b"\x07example\x03com\x00\x00\x02\x00\x01\x00\x00\x01\x2c\x00\x10");
+        bytes.extend_from_slice(b"\x02ns\x07example\x03com\x00");
+        // additional: A for mail.example.com
+        bytes.extend_from_slice(b"\x04mail\x07example\x03com\x00\x00\x01\x00\x01\x00\x00\x01\x2c\x00\x04\x0a\x00\x00\x01");
+
+        let message = Message::parse(&bytes).unwrap();
+        assert_eq!(message.get_packet().unwrap(), bytes);
+
+        let message = Message::parse(&example_response()).unwrap();
+        assert_eq!(message.get_packet().unwrap(), example_response());
+    }
+
+    #[test]
+    fn message_get_packet_keeps_compression() {
+        let mut bytes = example_response();
+        bytes[7] = 2;
+        // answer: www.example.com CNAME example.com, both names compressed
+        bytes.extend_from_slice(b"\x03www\xc0\x0c\x00\x05\x00\x01\x00\x00\x01\x2c\x00\x02\xc0\x0c");
+        let message = Message::parse(&bytes).unwrap();
+        assert_eq!(message.get_packet().unwrap(), bytes);
+
+        // a new ID keeps the records, a changed one is written in full
+        let mut changed = message.clone();
+        changed.header.set_id(0x4321);
+        changed.answer[1].ttl = 60;
+        let packet = changed.get_packet().unwrap();
+        assert_eq!(packet[..2], [0x43, 0x21]);
+        assert_eq!(packet[2..45], bytes[2..45]);
+        assert_eq!(&packet[45..62], b"\x03www\x07example\x03com\x00");
+        assert_eq!(Message::parse(&packet).unwrap(), changed);
+
+        // a changed record moves the records behind it, which are written in full
+        let mut changed = message.clone();
+        changed.answer[0].ttl = 60;
+        let packet = changed.get_packet().unwrap();
+        assert_eq!(packet.len(), bytes.len() + 3 * 11);
+        assert_eq!(Message::parse(&packet).unwrap(), changed);
+    }
+
+    #[test]
+    fn header_set_flags_allows_assigned_values() {
+        let mut options = Header::new();
+        // UPDATE response with AD and CD set and NOTZONE
+        options.set_flags(0b1010_1000_0011_1010).unwrap();
+        assert!(options.qr());
+        assert_eq!(options.opcode(), Opcode::Update);
+        assert!(options.ad() && options.cd());
+        assert_eq!(options.rcode(), Rcode::NotZone);
+        options.set_flags(0b0010_0000_0000_0000).unwrap();
+        assert_eq!(options.opcode(), Opcode::Notify);
+
+        assert!(options.set_flags(0b0001_1000_0000_0000).is_err());
+        assert!(options.set_flags(0b0000_0000_0100_0000).is_err());
+        assert!(options.set_flags(0b0000_0000_0000_1100).is_err());
+    }
+
+    #[test]
+    fn header_flag_setters() {
+        let mut options = Header::new();
+        options.set_qr(true);
+        options.set_aa(true);
+        options.set_tc(true);
+        options.set_rd(true);
+        options.set_ra(true);
+        options.set_opcode(Opcode::Status);
+        options.set_rcode(Rcode::BadCookie);
+        assert_eq!(options.flags(), 0b1001_0111_1000_0111);
+        assert_eq!(options.rcode(), Rcode::YXRRSet);
+        options.set_tc(false);
+        assert!(!options.tc());
+        assert!(options.aa() && options.rd() && options.ra());
+    }
 }
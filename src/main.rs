@@ -1,13 +1,11 @@
-use std::{error::Error, vec};
+use std::error::Error;
 
 use dns::*;
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let mut dns = Message::new();
-    dns.set_questions(vec![String::from("_minecraft._tcp.GrieferGames.de")])?;
-    dns.question[0].set_qtype(33)?;
-    dns.question[0].set_qclass(1)?;
-    let res = dns.send()?;
-    println!("{:#?}", res);
+    let resolver = Resolver::system();
+    for srv in resolver.lookup_srv("_minecraft._tcp.GrieferGames.de")? {
+        println!("{}:{} (priority {}, weight {})", srv.target, srv.port, srv.priority, srv.weight);
+    }
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,461 @@
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    net::IpAddr,
+    str::FromStr,
+};
+
+use crate::{dns_error::DnsError, wire::MAX_NAME_LEN};
+
+/// The maximum length of a single label in octets.
+const MAX_LABEL_LEN: usize = 63;
+
+/// A domain name.
+///
+/// The name is kept in its uncompressed wire format, a sequence of labels
+/// where each label consists of a length octet followed by that number of
+/// octets, terminated by the zero length label of the root.  Labels may hold
+/// any octet; comparison and hashing ignore ASCII case as required by
+/// RFC 4343, while the original case is kept for display and encoding.
+#[derive(Debug, Clone)]
+pub struct Name {
+    wire: Vec<u8>,
+}
+impl Name {
+    /// # Creates the root name
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use dns::Name;
+    ///
+    /// let root = Name::root();
+    /// assert!(root.is_root());
+    /// assert_eq!(root.to_string(), ".");
+    /// ```
+    pub fn root() -> Name {
+        Name { wire: vec![0] }
+    }
+
+    /// # Creates a name from its labels
+    ///
+    /// # Arguments
+    ///
+    /// takes the labels from left to right, without the root label.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use dns::Name;
+    ///
+    /// let name = Name::from_labels(["www", "example", "com"]).unwrap();
+    /// assert_eq!(name.to_string(), "www.example.com");
+    /// ```
+    pub fn from_labels<I, L>(labels: I) -> Result<Name, DnsError>
+    where
+        I: IntoIterator<Item = L>,
+        L: AsRef<[u8]>,
+    {
+        let mut wire = vec![];
+        for label in labels {
+            let label = label.as_ref();
+            if label.is_empty() {
+                return Err(invalid(&wire, "empty label"));
+            }
+            if label.len() > MAX_LABEL_LEN {
+                return Err(invalid(&wire, "label longer than 63 octets"));
+            }
+            wire.push(label.len() as u8);
+            wire.extend_from_slice(label);
+        }
+        wire.push(0);
+        if wire.len() > MAX_NAME_LEN {
+            return Err(invalid(&wire, "name longer than 255 octets"));
+        }
+        Ok(Name { wire })
+    }
+
+    /// # Creates the name of the PTR record of an address
+    ///
+    /// # Returns
+    ///
+    /// returns the octets of an IPv4 address in reverse below
+    /// `in-addr.arpa` (RFC 1035 3.5) and the nibbles of an IPv6 address in
+    /// reverse below `ip6.arpa` (RFC 3596 2.5).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use dns::Name;
+    /// use std::net::IpAddr;
+    ///
+    /// let name = Name::reverse("192.0.2.1".parse().unwrap());
+    /// assert_eq!(name.to_string(), "1.2.0.192.in-addr.arpa");
+    ///
+    /// let name = Name::reverse("2001:db8::1".parse().unwrap());
+    /// assert!(name.to_string().starts_with("1.0.0.0.0.0.0.0."));
+    /// assert!(name.to_string().ends_with(".8.b.d.0.1.0.0.2.ip6.arpa"));
+    /// ```
+    pub fn reverse(ip: IpAddr) -> Name {
+        let mut labels: Vec<String> = match ip {
+            IpAddr::V4(ip) => ip.octets().iter().rev().map(u8::to_string).collect(),
+            IpAddr::V6(ip) => ip
+                .octets()
+                .iter()
+                .rev()
+                .flat_map(|octet| [octet & 0xf, octet >> 4])
+                .map(|nibble| format!("{nibble:x}"))
+                .collect(),
+        };
+        match ip {
+            IpAddr::V4(_) => labels.extend(["in-addr".to_string(), "arpa".to_string()]),
+            IpAddr::V6(_) => labels.extend(["ip6".to_string(), "arpa".to_string()]),
+        }
+        Name::from_labels(labels).expect("reverse names are short")
+    }
+
+    /// Wraps label bytes that were already validated by the wire reader.
+    pub(crate) fn from_wire_unchecked(wire: Vec<u8>) -> Name {
+        Name { wire }
+    }
+
+    /// # Returns the uncompressed wire format
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use dns::Name;
+    ///
+    /// let name: Name = "example.com".parse().unwrap();
+    /// assert_eq!(name.as_wire(), b"\x07example\x03com\x00");
+    /// ```
+    pub fn as_wire(&self) -> &[u8] {
+        &self.wire
+    }
+
+    /// # Returns true for the root name
+    pub fn is_root(&self) -> bool {
+        self.wire.len() == 1
+    }
+
+    /// # Iterates over the labels from left to right, without the root label
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use dns::Name;
+    ///
+    /// let name: Name = "www.example.com".parse().unwrap();
+    /// let labels: Vec<&[u8]> = name.labels().collect();
+    /// assert_eq!(labels, vec![&b"www"[..], b"example", b"com"]);
+    /// ```
+    pub fn labels(&self) -> Labels<'_> {
+        Labels { wire: &self.wire }
+    }
+
+    /// # Returns the number of labels, not counting the root label
+    pub fn label_count(&self) -> usize {
+        self.labels().count()
+    }
+
+    /// # Returns the name without its leftmost label
+    ///
+    /// # Returns
+    ///
+    /// returns `None` for the root name.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use dns::Name;
+    ///
+    /// let name: Name = "www.example.com".parse().unwrap();
+    /// assert_eq!(name.parent().unwrap().to_string(), "example.com");
+    /// assert!(dns::Name::root().parent().is_none());
+    /// ```
+    pub fn parent(&self) -> Option<Name> {
+        if self.is_root() {
+            return None;
+        }
+        let skip = 1 + self.wire[0] as usize;
+        Some(Name {
+            wire: self.wire[skip..].to_vec(),
+        })
+    }
+
+    /// # Returns the name with `label` added on the left
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use dns::Name;
+    ///
+    /// let name: Name = "example.com".parse().unwrap();
+    /// assert_eq!(name.child("www").unwrap().to_string(), "www.example.com");
+    /// ```
+    pub fn child<L: AsRef<[u8]>>(&self, label: L) -> Result<Name, DnsError> {
+        Name::from_labels(std::iter::once(label.as_ref()).chain(self.labels()))
+    }
+
+    /// # Returns this name followed by all labels of `suffix`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use dns::Name;
+    ///
+    /// let host: Name = "www".parse().unwrap();
+    /// let domain: Name = "example.com".parse().unwrap();
+    /// assert_eq!(host.join(&domain).unwrap().to_string(), "www.example.com");
+    /// ```
+    pub fn join(&self, suffix: &Name) -> Result<Name, DnsError> {
+        Name::from_labels(self.labels().chain(suffix.labels()))
+    }
+
+    /// # Returns true if this name is `zone` or lies below it
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use dns::Name;
+    ///
+    /// let name: Name = "www.Example.com".parse().unwrap();
+    /// assert!(name.is_subdomain_of(&"example.COM".parse().unwrap()));
+    /// assert!(name.is_subdomain_of(&Name::root()));
+    /// assert!(!name.is_subdomain_of(&"ample.com".parse().unwrap()));
+    /// ```
+    pub fn is_subdomain_of(&self, zone: &Name) -> bool {
+        let count = self.label_count();
+        let zone_count = zone.label_count();
+        count >= zone_count && self.trim_to(zone_count) == *zone
+    }
+
+    /// # Returns the rightmost `count` labels of this name
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use dns::Name;
+    ///
+    /// let name: Name = "a.b.example.com".parse().unwrap();
+    /// assert_eq!(name.trim_to(2).to_string(), "example.com");
+    /// assert!(name.trim_to(0).is_root());
+    /// ```
+    pub fn trim_to(&self, count: usize) -> Name {
+        let skip = self.label_count().saturating_sub(count);
+        let mut i = 0;
+        for _ in 0..skip {
+            i += 1 + self.wire[i] as usize;
+        }
+        Name {
+            wire: self.wire[i..].to_vec(),
+        }
+    }
+
+    /// # Iterates over this name and all of its ancestors up to the root
+    ///
+    /// This is the order in which zone cuts are searched for a name.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use dns::Name;
+    ///
+    /// let name: Name = "www.example.com".parse().unwrap();
+    /// let names: Vec<String> = name.ancestors().map(|n| n.to_string()).collect();
+    /// assert_eq!(names, vec!["www.example.com", "example.com", "com", "."]);
+    /// ```
+    pub fn ancestors(&self) -> impl Iterator<Item = Name> {
+        std::iter::successors(Some(self.clone()), Name::parent)
+    }
+
+    /// # Returns the longest common suffix of both names
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use dns::Name;
+    ///
+    /// let a: Name = "www.example.com".parse().unwrap();
+    /// let b: Name = "mail.example.com".parse().unwrap();
+    /// assert_eq!(a.common_ancestor(&b).to_string(), "example.com");
+    /// ```
+    pub fn common_ancestor(&self, other: &Name) -> Name {
+        let mut count = self.label_count().min(other.label_count());
+        while self.trim_to(count) != other.trim_to(count) {
+            count -= 1;
+        }
+        self.trim_to(count)
+    }
+}
+impl Default for Name {
+    fn default() -> Self {
+        Name::root()
+    }
+}
+
+fn invalid(wire: &[u8], reason: &'static str) -> DnsError {
+    let name = Name::from_wire_unchecked(wire.to_vec());
+    let mut text = String::new();
+    for label in name.labels() {
+        write_label(&mut text, label);
+        text.push('.');
+    }
+    DnsError::InvalidName { name: text, reason }
+}
+
+impl PartialEq for Name {
+    fn eq(&self, other: &Self) -> bool {
+        self.wire.eq_ignore_ascii_case(&other.wire)
+    }
+}
+impl Eq for Name {}
+
+impl Hash for Name {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for b in &self.wire {
+            state.write_u8(b.to_ascii_lowercase());
+        }
+    }
+}
+
+/// # Parses a name in presentation format
+///
+/// Labels are separated by dots and a trailing dot is optional, every name
+/// is treated as fully qualified.  `\.` and `\\` escape a literal dot or
+/// backslash inside a label and `\DDD` inserts the octet with the decimal
+/// value `DDD`.  Other non-ASCII characters are rejected, internationalized
+/// names have to be given in their punycode form.
+impl FromStr for Name {
+    type Err = DnsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = |reason| DnsError::InvalidName {
+            name: s.to_string(),
+            reason,
+        };
+        if s == "." || s.is_empty() {
+            return Ok(Name::root());
+        }
+        let mut labels = vec![];
+        let mut label = vec![];
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '.' => labels.push(std::mem::take(&mut label)),
+                '\\' => match chars.next() {
+                    Some(d) if d.is_ascii_digit() => {
+                        let digits: String = std::iter::once(d).chain(chars.by_ref().take(2)).collect();
+                        if digits.len() != 3 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                            return Err(err("\\DDD escape needs three digits"));
+                        }
+                        let value: u16 = digits.parse().map_err(|_| err("invalid \\DDD escape"))?;
+                        label.push(u8::try_from(value).map_err(|_| err("\\DDD escape above 255"))?);
+                    }
+                    Some(c) if c.is_ascii() => label.push(c as u8),
+                    Some(_) => return Err(err("non-ASCII character")),
+                    None => return Err(err("dangling escape")),
+                },
+                c if c.is_ascii() => label.push(c as u8),
+                _ => return Err(err("non-ASCII character")),
+            }
+        }
+        // an empty last label is the optional trailing dot
+        if !label.is_empty() {
+            labels.push(label);
+        }
+        Name::from_labels(labels).map_err(|e| match e {
+            DnsError::InvalidName { reason, .. } => err(reason),
+            e => e,
+        })
+    }
+}
+
+/// Writes a label in presentation format, escaping what [`Name::from_str`]
+/// would not read back as the same octet.
+fn write_label(out: &mut String, label: &[u8]) {
+    for &b in label {
+        match b {
+            b'.' | b'\\' => {
+                out.push('\\');
+                out.push(b as char);
+            }
+            0x21..=0x7e => out.push(b as char),
+            _ => out.push_str(&format!("\\{b:03}")),
+        }
+    }
+}
+
+/// Writes the name in presentation format without the trailing dot, the
+/// root name is written as `.`.
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_root() {
+            return f.write_str(".");
+        }
+        let mut text = String::new();
+        for (i, label) in self.labels().enumerate() {
+            if i > 0 {
+                text.push('.');
+            }
+            write_label(&mut text, label);
+        }
+        f.write_str(&text)
+    }
+}
+
+/// Iterator over the labels of a [`Name`], see [`Name::labels`].
+pub struct Labels<'a> {
+    wire: &'a [u8],
+}
+impl<'a> Iterator for Labels<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let len = *self.wire.first()? as usize;
+        if len == 0 {
+            return None;
+        }
+        let label = &self.wire[1..1 + len];
+        self.wire = &self.wire[1 + len..];
+        Some(label)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn name_parses_escapes() {
+        let name: Name = r"a\.b.c\\d.\065\255.".parse().unwrap();
+        assert_eq!(name.as_wire(), b"\x03a.b\x03c\\d\x02A\xff\x00");
+        assert_eq!(name.to_string(), r"a\.b.c\\d.A\255");
+        assert_eq!(name.to_string().parse::<Name>().unwrap(), name);
+        assert_eq!(r"a\\.".parse::<Name>().unwrap().as_wire(), b"\x02a\\\x00");
+    }
+
+    #[test]
+    fn name_rejects_invalid_input() {
+        for text in ["a..b", "a..", ".a", "bücher.de", r"a\25", r"a\256", "a\\"] {
+            assert!(text.parse::<Name>().is_err(), "{text} parsed");
+        }
+        assert!(["a"; 63].concat().parse::<Name>().is_ok());
+        assert!(["a"; 64].concat().parse::<Name>().is_err());
+        // 4 labels of 63 octets are 256 octets on the wire
+        let long = vec!["a".repeat(63); 4].join(".");
+        assert!(long.parse::<Name>().is_err());
+        assert!(long[2..].parse::<Name>().is_ok());
+    }
+
+    #[test]
+    fn name_equality_ignores_case() {
+        let a: Name = "WWW.Example.com".parse().unwrap();
+        let b: Name = "www.example.COM.".parse().unwrap();
+        assert_eq!(a, b);
+        assert_eq!(a.to_string(), "WWW.Example.com");
+        let set: HashSet<Name> = [a, b].into_iter().collect();
+        assert_eq!(set.len(), 1);
+    }
+}
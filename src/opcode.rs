@@ -0,0 +1,77 @@
+use std::fmt;
+
+/// The kind of query in a message, set by the originator of a query and
+/// copied into the response.
+///
+/// # Example
+///
+/// ```
+/// use dns::{Header, Opcode};
+///
+/// let mut header = Header::new();
+/// header.set_opcode(Opcode::Notify);
+/// assert_eq!(header.opcode(), Opcode::Notify);
+/// assert_eq!(u8::from(Opcode::Update), 5);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Opcode {
+    /// a standard query (QUERY), RFC 1035
+    Query,
+    /// an inverse query (IQUERY), obsoleted by RFC 3425
+    IQuery,
+    /// a server status request (STATUS), RFC 1035
+    Status,
+    /// a zone change notification (NOTIFY), RFC 1996
+    Notify,
+    /// a dynamic update (UPDATE), RFC 2136
+    Update,
+    /// DNS stateful operations (DSO), RFC 8490
+    Dso,
+    /// An unassigned opcode, only the low four bits are used.
+    Unknown(u8),
+}
+impl Opcode {
+    /// # Returns true for the opcodes assigned by IANA
+    pub fn is_assigned(&self) -> bool {
+        !matches!(self, Opcode::Unknown(_))
+    }
+}
+impl From<u8> for Opcode {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Opcode::Query,
+            1 => Opcode::IQuery,
+            2 => Opcode::Status,
+            4 => Opcode::Notify,
+            5 => Opcode::Update,
+            6 => Opcode::Dso,
+            _ => Opcode::Unknown(value & 0b1111),
+        }
+    }
+}
+impl From<Opcode> for u8 {
+    fn from(value: Opcode) -> Self {
+        match value {
+            Opcode::Query => 0,
+            Opcode::IQuery => 1,
+            Opcode::Status => 2,
+            Opcode::Notify => 4,
+            Opcode::Update => 5,
+            Opcode::Dso => 6,
+            Opcode::Unknown(value) => value & 0b1111,
+        }
+    }
+}
+impl fmt::Display for Opcode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Opcode::Query => f.write_str("QUERY"),
+            Opcode::IQuery => f.write_str("IQUERY"),
+            Opcode::Status => f.write_str("STATUS"),
+            Opcode::Notify => f.write_str("NOTIFY"),
+            Opcode::Update => f.write_str("UPDATE"),
+            Opcode::Dso => f.write_str("DSO"),
+            Opcode::Unknown(value) => write!(f, "OPCODE{value}"),
+        }
+    }
+}
@@ -0,0 +1,313 @@
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::{mpsc, Arc, Mutex},
+    time::Duration,
+};
+
+use quinn::{crypto::rustls::QuicClientConfig, Connection, Endpoint};
+use tokio::runtime::Runtime;
+
+use crate::{dns_error::DnsError, tls::tls_error, transport::Pool, Message, TlsConfig, Transport};
+
+/// The port of DNS over QUIC.
+pub const DOQ_PORT: u16 = 853;
+
+/// A DNS over QUIC connection to one server (RFC 9250).
+///
+/// Every query gets a stream of its own on one connection, which is opened
+/// by the first query and kept for the next ones.  The query goes out with
+/// ID 0 as the RFC requires, the reply gets the ID of the query back.  The
+/// server is authenticated like a DNS over TLS server, see [`TlsConfig`].
+///
+/// The connection is driven by a tokio runtime of the client's own, the
+/// calling thread waits for the reply like with the other protocols.  It
+/// may be called from async code, but blocks the worker thread while it
+/// waits, see [`crate::Protocol::Quic`].
+///
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use dns::{Message, QuicClient, TlsConfig};
+///
+/// let mut tls = TlsConfig::new();
+/// tls.set_server_name("dns.adguard-dns.com");
+/// let client = QuicClient::new("94.140.14.14:853".parse().unwrap(), tls, Duration::from_secs(5)).unwrap();
+///
+/// let mut message = Message::new();
+/// message.set_questions(vec!["example.com".to_string()]).unwrap();
+/// let response = client.send(&message).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct QuicClient {
+    shared: Arc<Shared>,
+    timeout: Duration,
+    /// only taken when the client is dropped
+    runtime: Option<Runtime>,
+}
+
+/// What the queries running on the runtime of a [`QuicClient`] share.
+#[derive(Debug)]
+struct Shared {
+    server: SocketAddr,
+    name: String,
+    config: quinn::ClientConfig,
+    endpoint: Endpoint,
+    connection: Mutex<Option<Connection>>,
+}
+
+impl QuicClient {
+    /// # Creates a new QuicClient
+    ///
+    /// # Errors
+    ///
+    /// returns [`DnsError::TlsError`] if a root certificate or the
+    /// authentication name is invalid and [`DnsError::QuicError`] if no UDP
+    /// socket could be opened.
+    pub fn new(server: SocketAddr, tls: TlsConfig, timeout: Duration) -> Result<QuicClient, DnsError> {
+        // checks the name the same way as DNS over TLS
+        tls.name_for(server)?;
+        let name = match tls.server_name() {
+            Some(name) => name.to_string(),
+            None => server.ip().to_string(),
+        };
+        let crypto = QuicClientConfig::try_from(Arc::new(tls.client_config(b"doq")?)).map_err(tls_error)?;
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()
+            .map_err(DnsError::QuicError)?;
+        let local = match server {
+            SocketAddr::V4(_) => SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0),
+            SocketAddr::V6(_) => SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0),
+        };
+        // the endpoint needs the runtime to spawn its driver
+        let endpoint = {
+            let _guard = runtime.enter();
+            Endpoint::client(local).map_err(DnsError::QuicError)?
+        };
+        Ok(QuicClient {
+            shared: Arc::new(Shared {
+                server,
+                name,
+                config: quinn::ClientConfig::new(Arc::new(crypto)),
+                endpoint,
+                connection: Mutex::new(None),
+            }),
+            timeout,
+            runtime: Some(runtime),
+        })
+    }
+
+    /// # Returns the server
+    pub fn server(&self) -> SocketAddr {
+        self.shared.server
+    }
+
+    /// # Sends the message and waits for the reply
+    pub fn send(&self, message: &Message) -> Result<Message, DnsError> {
+        self.exchange(message, self.timeout)
+    }
+
+    /// Sends the message, waiting up to `timeout`.
+    ///
+    /// The exchange runs as a task on the runtime of the client and hands
+    /// the reply back over a channel, `block_on` would panic when called
+    /// from async code.
+    pub(crate) fn exchange(&self, message: &Message, timeout: Duration) -> Result<Message, DnsError> {
+        let mut query = message.clone();
+        query.header.set_id(0);
+        let data = query.encode()?;
+        if data.len() > u16::MAX as usize {
+            return Err(DnsError::MessageTooLong(data.len()));
+        }
+        let shared = self.shared.clone();
+        let exchange = async move {
+            let connection = shared.connection().await?;
+            let (mut send, mut recv) = connection.open_bi().await.map_err(quic_error)?;
+            let mut frame = (data.len() as u16).to_be_bytes().to_vec();
+            frame.extend_from_slice(&data);
+            send.write_all(&frame).await.map_err(quic_error)?;
+            // the end of the stream tells the server the query is complete
+            send.finish().map_err(quic_error)?;
+            let mut len = [0; 2];
+            recv.read_exact(&mut len).await.map_err(quic_error)?;
+            let mut buf = vec![0; u16::from_be_bytes(len) as usize];
+            recv.read_exact(&mut buf).await.map_err(quic_error)?;
+            Ok::<_, DnsError>(buf)
+        };
+        let (tx, rx) = mpsc::channel();
+        let runtime = self.runtime.as_ref().expect("the runtime is only taken on drop");
+        runtime.spawn(async move {
+            let _ = tx.send(tokio::time::timeout(timeout, exchange).await);
+        });
+        let buf = rx
+            .recv()
+            .map_err(|_| quic_error("the runtime of the client stopped"))?
+            .map_err(|_| DnsError::Timeout { server: self.shared.server, timeout })??;
+        let mut response = Message::parse(&buf)?;
+        if !response.is_response_to(&query) {
+            return Err(DnsError::MismatchedResponse);
+        }
+        response.header.set_id(message.header.id);
+        Ok(response)
+    }
+}
+impl Shared {
+    /// Returns the open connection, or opens a new one.
+    async fn connection(&self) -> Result<Connection, DnsError> {
+        if let Some(connection) = self.connection.lock().unwrap().as_ref() {
+            if connection.close_reason().is_none() {
+                return Ok(connection.clone());
+            }
+        }
+        let connection = self
+            .endpoint
+            .connect_with(self.config.clone(), self.server, &self.name)
+            .map_err(quic_error)?
+            .await
+            .map_err(quic_error)?;
+        *self.connection.lock().unwrap() = Some(connection.clone());
+        Ok(connection)
+    }
+}
+impl Drop for QuicClient {
+    fn drop(&mut self) {
+        // DOQ_NO_ERROR
+        self.shared.endpoint.close(0u32.into(), b"");
+        // dropping a runtime waits for its threads, which panics in async code
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+/// Sends queries over DNS over QUIC, one [`QuicClient`] per server, all
+/// authenticated as set by the [`TlsConfig`].
+#[derive(Debug)]
+pub struct QuicTransport {
+    tls: TlsConfig,
+    connections: Pool<QuicClient>,
+}
+impl QuicTransport {
+    /// # Creates a new QuicTransport
+    pub fn new(tls: TlsConfig) -> QuicTransport {
+        QuicTransport {
+            tls,
+            connections: Pool::new(),
+        }
+    }
+}
+impl Transport for QuicTransport {
+    fn send(&self, server: SocketAddr, query: &Message, timeout: Duration) -> Result<Message, DnsError> {
+        let connection = self.connections.get(server, || QuicClient::new(server, self.tls.clone(), timeout))?;
+        connection.exchange(query, timeout)
+    }
+}
+
+fn quic_error<E: Into<Box<dyn std::error::Error + Send + Sync>>>(e: E) -> DnsError {
+    DnsError::QuicError(io::Error::other(e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Client, ClientConfig, Protocol, RData, Resource};
+    use quinn::crypto::rustls::QuicServerConfig;
+    use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+    use std::{sync::mpsc, thread};
+
+    /// Answers the queries on the first connection with an A record,
+    /// reporting the IDs of the queries.
+    fn quic_server() -> (SocketAddr, TlsConfig, mpsc::Receiver<u16>) {
+        let key = rcgen::KeyPair::generate().unwrap();
+        let cert = rcgen::CertificateParams::new(vec!["dns.test".to_string()])
+            .unwrap()
+            .self_signed(&key)
+            .unwrap();
+        let mut tls = TlsConfig::new();
+        tls.clear_root_certificates();
+        tls.add_spki_pin(TlsConfig::spki_pin(cert.der()).unwrap());
+        tls.set_server_name("dns.test");
+
+        let mut crypto = rustls::ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(
+                vec![CertificateDer::from(cert.der().to_vec())],
+                PrivateKeyDer::Pkcs8(key.serialize_der().into()),
+            )
+            .unwrap();
+        crypto.alpn_protocols = vec![b"doq".to_vec()];
+        let config = quinn::ServerConfig::with_crypto(Arc::new(QuicServerConfig::try_from(crypto).unwrap()));
+
+        let (addr_tx, addr_rx) = mpsc::channel();
+        let (ids, ids_rx) = mpsc::channel();
+        thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+            runtime.block_on(async move {
+                let endpoint = Endpoint::server(config, "127.0.0.1:0".parse().unwrap()).unwrap();
+                addr_tx.send(endpoint.local_addr().unwrap()).unwrap();
+                let connection = endpoint.accept().await.unwrap().await.unwrap();
+                while let Ok((mut send, mut recv)) = connection.accept_bi().await {
+                    let frame = recv.read_to_end(65537).await.unwrap();
+                    let mut response = Message::parse(&frame[2..]).unwrap();
+                    ids.send(response.header.id).unwrap();
+                    response.header.set_qr(true);
+                    let name = response.question[0].qname.clone();
+                    response.answer.push(Resource::new(name, 60, RData::A([192, 0, 2, 1].into())).unwrap());
+                    let data = response.encode().unwrap();
+                    send.write_all(&(data.len() as u16).to_be_bytes()).await.unwrap();
+                    send.write_all(&data).await.unwrap();
+                    send.finish().unwrap();
+                    // waits until the client has the reply
+                    let _ = send.stopped().await;
+                }
+            });
+        });
+        (addr_rx.recv().unwrap(), tls, ids_rx)
+    }
+
+    fn query(name: &str) -> Message {
+        let mut message = Message::new();
+        message.set_questions(vec![name.to_string()]).unwrap();
+        message.header.set_id(0x1234);
+        message
+    }
+
+    #[test]
+    fn quic_client_sends_one_stream_per_query() {
+        let (addr, tls, ids) = quic_server();
+        let client = QuicClient::new(addr, tls, Duration::from_secs(5)).unwrap();
+
+        for name in ["a.example", "b.example"] {
+            let response = client.send(&query(name)).unwrap();
+            assert_eq!(response.header.id, 0x1234);
+            assert_eq!(response.answer.len(), 1);
+        }
+        // both streams were on the one connection the server accepts
+        assert_eq!(ids.try_iter().collect::<Vec<_>>(), vec![0, 0]);
+    }
+
+    #[test]
+    fn client_sends_over_quic() {
+        let (addr, tls, _ids) = quic_server();
+        let mut config = ClientConfig::new();
+        config.set_servers(vec![addr]);
+        config.set_protocol(Protocol::Quic);
+        config.set_tls(tls);
+
+        let response = Client::new(config).send(&query("a.example")).unwrap();
+        assert_eq!(response.answer.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn quic_client_can_be_used_from_async_code() {
+        let (addr, tls, _ids) = quic_server();
+        let client = QuicClient::new(addr, tls, Duration::from_secs(5)).unwrap();
+        assert_eq!(client.send(&query("a.example")).unwrap().answer.len(), 1);
+        drop(client);
+    }
+}
@@ -0,0 +1,164 @@
+use std::fmt;
+
+/// The response code of a message.
+///
+/// The header only holds the low four bits; the codes from 16 upwards are
+/// extended RCODEs that need the upper eight bits carried in the EDNS OPT
+/// record (RFC 6891), or are only used inside TSIG and TKEY records.
+///
+/// # Example
+///
+/// ```
+/// use dns::Rcode;
+///
+/// assert_eq!(Rcode::from(3), Rcode::NXDomain);
+/// assert_eq!(u16::from(Rcode::BadCookie), 23);
+/// assert_eq!(Rcode::from(4000).to_string(), "RCODE4000");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rcode {
+    /// No error condition
+    NoError,
+    /// Format error - The name server was unable to interpret the query.
+    FormErr,
+    /// Server failure - The name server was unable to process this query
+    /// due to a problem with the name server.
+    ServFail,
+    /// Name Error - The domain name referenced in the query does not exist.
+    NXDomain,
+    /// Not Implemented - The name server does not support the requested
+    /// kind of query.
+    NotImp,
+    /// Refused - The name server refuses to perform the specified operation
+    /// for policy reasons.
+    Refused,
+    /// A name exists when it should not (RFC 2136).
+    YXDomain,
+    /// An RR set exists when it should not (RFC 2136).
+    YXRRSet,
+    /// An RR set that should exist does not (RFC 2136).
+    NXRRSet,
+    /// The server is not authoritative for the zone, or the request is not
+    /// authorized (RFC 2136, RFC 8945).
+    NotAuth,
+    /// A name is not contained in the zone (RFC 2136).
+    NotZone,
+    /// The DSO-TYPE is not implemented (RFC 8490).
+    DsoTypeNI,
+    /// Bad OPT version (RFC 6891), also TSIG signature failure (BADSIG).
+    BadVers,
+    /// Key not recognized (RFC 8945).
+    BadKey,
+    /// Signature out of time window (RFC 8945).
+    BadTime,
+    /// Bad TKEY mode (RFC 2930).
+    BadMode,
+    /// Duplicate key name (RFC 2930).
+    BadName,
+    /// Algorithm not supported (RFC 2930).
+    BadAlg,
+    /// Bad truncation (RFC 8945).
+    BadTrunc,
+    /// Bad or missing server cookie (RFC 7873).
+    BadCookie,
+    /// An unassigned response code, up to twelve bits.
+    Unknown(u16),
+}
+impl Rcode {
+    /// # Returns the low four bits that go into the header
+    pub fn low(&self) -> u8 {
+        (u16::from(*self) & 0b1111) as u8
+    }
+
+    /// # Returns the upper eight bits that go into the OPT record
+    pub fn high(&self) -> u8 {
+        (u16::from(*self) >> 4) as u8
+    }
+
+    /// # Combines the header bits and the OPT record bits
+    pub fn from_parts(low: u8, high: u8) -> Rcode {
+        Rcode::from((high as u16) << 4 | (low & 0b1111) as u16)
+    }
+}
+impl From<u16> for Rcode {
+    fn from(value: u16) -> Self {
+        match value {
+            0 => Rcode::NoError,
+            1 => Rcode::FormErr,
+            2 => Rcode::ServFail,
+            3 => Rcode::NXDomain,
+            4 => Rcode::NotImp,
+            5 => Rcode::Refused,
+            6 => Rcode::YXDomain,
+            7 => Rcode::YXRRSet,
+            8 => Rcode::NXRRSet,
+            9 => Rcode::NotAuth,
+            10 => Rcode::NotZone,
+            11 => Rcode::DsoTypeNI,
+            16 => Rcode::BadVers,
+            17 => Rcode::BadKey,
+            18 => Rcode::BadTime,
+            19 => Rcode::BadMode,
+            20 => Rcode::BadName,
+            21 => Rcode::BadAlg,
+            22 => Rcode::BadTrunc,
+            23 => Rcode::BadCookie,
+            _ => Rcode::Unknown(value & 0x0fff),
+        }
+    }
+}
+impl From<Rcode> for u16 {
+    fn from(value: Rcode) -> Self {
+        match value {
+            Rcode::NoError => 0,
+            Rcode::FormErr => 1,
+            Rcode::ServFail => 2,
+            Rcode::NXDomain => 3,
+            Rcode::NotImp => 4,
+            Rcode::Refused => 5,
+            Rcode::YXDomain => 6,
+            Rcode::YXRRSet => 7,
+            Rcode::NXRRSet => 8,
+            Rcode::NotAuth => 9,
+            Rcode::NotZone => 10,
+            Rcode::DsoTypeNI => 11,
+            Rcode::BadVers => 16,
+            Rcode::BadKey => 17,
+            Rcode::BadTime => 18,
+            Rcode::BadMode => 19,
+            Rcode::BadName => 20,
+            Rcode::BadAlg => 21,
+            Rcode::BadTrunc => 22,
+            Rcode::BadCookie => 23,
+            Rcode::Unknown(value) => value & 0x0fff,
+        }
+    }
+}
+impl fmt::Display for Rcode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Rcode::NoError => "NOERROR",
+            Rcode::FormErr => "FORMERR",
+            Rcode::ServFail => "SERVFAIL",
+            Rcode::NXDomain => "NXDOMAIN",
+            Rcode::NotImp => "NOTIMP",
+            Rcode::Refused => "REFUSED",
+            Rcode::YXDomain => "YXDOMAIN",
+            Rcode::YXRRSet => "YXRRSET",
+            Rcode::NXRRSet => "NXRRSET",
+            Rcode::NotAuth => "NOTAUTH",
+            Rcode::NotZone => "NOTZONE",
+            Rcode::DsoTypeNI => "DSOTYPENI",
+            Rcode::BadVers => "BADVERS",
+            Rcode::BadKey => "BADKEY",
+            Rcode::BadTime => "BADTIME",
+            Rcode::BadMode => "BADMODE",
+            Rcode::BadName => "BADNAME",
+            Rcode::BadAlg => "BADALG",
+            Rcode::BadTrunc => "BADTRUNC",
+            Rcode::BadCookie => "BADCOOKIE",
+            Rcode::Unknown(value) => return write!(f, "RCODE{value}"),
+        };
+        f.write_str(name)
+    }
+}
@@ -0,0 +1,393 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use crate::{dns_error::DnsError, wire::Reader, Name, RecordType};
+
+/// The decoded RDATA of a resource record.
+///
+/// Types without a decoder are kept as [`RData::Unknown`] with their raw
+/// bytes, so they can be written back unchanged (RFC 3597).
+///
+/// # Example
+///
+/// ```
+/// use dns::{Mx, RData, RecordType};
+///
+/// let mx = RData::MX(Mx {
+///     preference: 10,
+///     exchange: "mail.example.com".parse().unwrap(),
+/// });
+/// let bytes = mx.encode().unwrap();
+/// assert_eq!(RData::decode(RecordType::MX, &bytes).unwrap(), mx);
+/// ```
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RData {
+    /// a host address
+    A(Ipv4Addr),
+    /// an IPv6 host address
+    AAAA(Ipv6Addr),
+    /// an authoritative name server
+    NS(Name),
+    /// the canonical name for an alias
+    CNAME(Name),
+    /// a domain name pointer
+    PTR(Name),
+    /// mail exchange
+    MX(Mx),
+    /// one or more character strings
+    TXT(Vec<Vec<u8>>),
+    /// start of a zone of authority
+    SOA(Soa),
+    /// server selection
+    SRV(Srv),
+    /// certification authority authorization
+    CAA(Caa),
+    /// naming authority pointer
+    NAPTR(Naptr),
+    /// host information
+    HINFO(Hinfo),
+    /// the target of a redirection of the names below the owner (RFC 6672)
+    DNAME(Name),
+    /// RDATA of any other type, kept as it was received.
+    Unknown { rtype: RecordType, data: Vec<u8> },
+}
+
+/// The RDATA of an MX record (RFC 1035).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mx {
+    /// the preference of this exchange, lower values are preferred.
+    pub preference: u16,
+    /// a host willing to act as a mail exchange.
+    pub exchange: Name,
+}
+
+/// The RDATA of an SOA record (RFC 1035).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Soa {
+    /// the name server that was the primary source of data for this zone.
+    pub mname: Name,
+    /// the mailbox of the person responsible for this zone.
+    pub rname: Name,
+    /// the version number of the original copy of the zone.
+    pub serial: u32,
+    /// the interval before the zone should be refreshed.
+    pub refresh: u32,
+    /// the interval before a failed refresh should be retried.
+    pub retry: u32,
+    /// the upper limit on the time before the zone is no longer authoritative.
+    pub expire: u32,
+    /// the TTL for negative responses (RFC 2308).
+    pub minimum: u32,
+}
+
+/// The RDATA of an SRV record (RFC 2782).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Srv {
+    /// the priority of this target host, lower values are preferred.
+    pub priority: u16,
+    /// the relative weight for entries with the same priority.
+    pub weight: u16,
+    /// the port of the service on the target host.
+    pub port: u16,
+    /// the domain name of the target host.
+    pub target: Name,
+}
+
+/// The RDATA of a CAA record (RFC 8659).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Caa {
+    /// the flags, bit 7 is the issuer critical flag.
+    pub flags: u8,
+    /// the property tag, like `issue` or `iodef`.
+    pub tag: String,
+    /// the property value.
+    pub value: Vec<u8>,
+}
+
+/// The RDATA of a NAPTR record (RFC 3403).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Naptr {
+    /// the order in which the records must be processed.
+    pub order: u16,
+    /// the order for records with equal `order`.
+    pub preference: u16,
+    /// flags that control the rewriting and interpretation.
+    pub flags: Vec<u8>,
+    /// the service parameters.
+    pub services: Vec<u8>,
+    /// the substitution expression.
+    pub regexp: Vec<u8>,
+    /// the next name to query.
+    pub replacement: Name,
+}
+
+/// The RDATA of an HINFO record (RFC 1035).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hinfo {
+    /// the CPU type.
+    pub cpu: Vec<u8>,
+    /// the operating system type.
+    pub os: Vec<u8>,
+}
+
+impl RData {
+    /// # Decodes RDATA
+    ///
+    /// # Arguments
+    ///
+    /// takes the record type and the uncompressed RDATA, as kept in
+    /// [`crate::Resource::rdata`].
+    ///
+    /// # Returns
+    ///
+    /// returns [`DnsError::MalformedRdata`] if the bytes do not have the
+    /// layout of the type.
+    pub fn decode(rtype: RecordType, rdata: &[u8]) -> Result<RData, DnsError> {
+        let mut reader = Reader::new(rdata);
+        let data = RData::read(rtype, &mut reader).map_err(|_| DnsError::MalformedRdata(rtype))?;
+        if !reader.is_empty() {
+            return Err(DnsError::MalformedRdata(rtype));
+        }
+        Ok(data)
+    }
+
+    fn read(rtype: RecordType, reader: &mut Reader) -> Result<RData, DnsError> {
+        let data = match rtype {
+            RecordType::A => {
+                let b = reader.bytes(4)?;
+                RData::A(Ipv4Addr::new(b[0], b[1], b[2], b[3]))
+            }
+            RecordType::AAAA => {
+                let mut octets = [0; 16];
+                octets.copy_from_slice(reader.bytes(16)?);
+                RData::AAAA(Ipv6Addr::from(octets))
+            }
+            RecordType::NS => RData::NS(reader.name()?),
+            RecordType::CNAME => RData::CNAME(reader.name()?),
+            RecordType::PTR => RData::PTR(reader.name()?),
+            RecordType::MX => RData::MX(Mx {
+                preference: reader.u16()?,
+                exchange: reader.name()?,
+            }),
+            RecordType::TXT => {
+                let mut strings = vec![reader.character_string()?.to_vec()];
+                while !reader.is_empty() {
+                    strings.push(reader.character_string()?.to_vec());
+                }
+                RData::TXT(strings)
+            }
+            RecordType::SOA => RData::SOA(Soa {
+                mname: reader.name()?,
+                rname: reader.name()?,
+                serial: reader.u32()?,
+                refresh: reader.u32()?,
+                retry: reader.u32()?,
+                expire: reader.u32()?,
+                minimum: reader.u32()?,
+            }),
+            RecordType::SRV => RData::SRV(Srv {
+                priority: reader.u16()?,
+                weight: reader.u16()?,
+                port: reader.u16()?,
+                target: reader.name()?,
+            }),
+            RecordType::CAA => {
+                let flags = reader.u8()?;
+                let tag = reader.character_string()?;
+                if tag.is_empty() || !tag.iter().all(u8::is_ascii_alphanumeric) {
+                    return Err(DnsError::MalformedRdata(rtype));
+                }
+                RData::CAA(Caa {
+                    flags,
+                    tag: String::from_utf8_lossy(tag).into_owned(),
+                    value: reader.rest().to_vec(),
+                })
+            }
+            RecordType::NAPTR => RData::NAPTR(Naptr {
+                order: reader.u16()?,
+                preference: reader.u16()?,
+                flags: reader.character_string()?.to_vec(),
+                services: reader.character_string()?.to_vec(),
+                regexp: reader.character_string()?.to_vec(),
+                replacement: reader.name()?,
+            }),
+            RecordType::HINFO => RData::HINFO(Hinfo {
+                cpu: reader.character_string()?.to_vec(),
+                os: reader.character_string()?.to_vec(),
+            }),
+            RecordType::DNAME => RData::DNAME(reader.name()?),
+            _ => RData::Unknown {
+                rtype,
+                data: reader.rest().to_vec(),
+            },
+        };
+        Ok(data)
+    }
+
+    /// # Returns the record type this RDATA belongs to
+    pub fn rtype(&self) -> RecordType {
+        match self {
+            RData::A(_) => RecordType::A,
+            RData::AAAA(_) => RecordType::AAAA,
+            RData::NS(_) => RecordType::NS,
+            RData::CNAME(_) => RecordType::CNAME,
+            RData::PTR(_) => RecordType::PTR,
+            RData::MX(_) => RecordType::MX,
+            RData::TXT(_) => RecordType::TXT,
+            RData::SOA(_) => RecordType::SOA,
+            RData::SRV(_) => RecordType::SRV,
+            RData::CAA(_) => RecordType::CAA,
+            RData::NAPTR(_) => RecordType::NAPTR,
+            RData::HINFO(_) => RecordType::HINFO,
+            RData::DNAME(_) => RecordType::DNAME,
+            RData::Unknown { rtype, .. } => *rtype,
+        }
+    }
+
+    /// # Encodes the RDATA without name compression
+    ///
+    /// # Returns
+    ///
+    /// returns [`DnsError::MalformedRdata`] if a character string is longer
+    /// than 255 octets or a CAA tag is empty or not alphanumeric.
+    pub fn encode(&self) -> Result<Vec<u8>, DnsError> {
+        let mut out = vec![];
+        let string = |out: &mut Vec<u8>, s: &[u8]| {
+            let len = u8::try_from(s.len()).map_err(|_| DnsError::MalformedRdata(self.rtype()))?;
+            out.push(len);
+            out.extend_from_slice(s);
+            Ok::<(), DnsError>(())
+        };
+        match self {
+            RData::A(addr) => out.extend_from_slice(&addr.octets()),
+            RData::AAAA(addr) => out.extend_from_slice(&addr.octets()),
+            RData::NS(name) | RData::CNAME(name) | RData::PTR(name) | RData::DNAME(name) => {
+                out.extend_from_slice(name.as_wire())
+            }
+            RData::MX(mx) => {
+                out.extend_from_slice(&mx.preference.to_be_bytes());
+                out.extend_from_slice(mx.exchange.as_wire());
+            }
+            RData::TXT(strings) => {
+                for s in strings {
+                    string(&mut out, s)?;
+                }
+            }
+            RData::SOA(soa) => {
+                out.extend_from_slice(soa.mname.as_wire());
+                out.extend_from_slice(soa.rname.as_wire());
+                for value in [soa.serial, soa.refresh, soa.retry, soa.expire, soa.minimum] {
+                    out.extend_from_slice(&value.to_be_bytes());
+                }
+            }
+            RData::SRV(srv) => {
+                for value in [srv.priority, srv.weight, srv.port] {
+                    out.extend_from_slice(&value.to_be_bytes());
+                }
+                out.extend_from_slice(srv.target.as_wire());
+            }
+            RData::CAA(caa) => {
+                if caa.tag.is_empty() || !caa.tag.bytes().all(|b| b.is_ascii_alphanumeric()) {
+                    return Err(DnsError::MalformedRdata(RecordType::CAA));
+                }
+                out.push(caa.flags);
+                string(&mut out, caa.tag.as_bytes())?;
+                out.extend_from_slice(&caa.value);
+            }
+            RData::NAPTR(naptr) => {
+                out.extend_from_slice(&naptr.order.to_be_bytes());
+                out.extend_from_slice(&naptr.preference.to_be_bytes());
+                string(&mut out, &naptr.flags)?;
+                string(&mut out, &naptr.services)?;
+                string(&mut out, &naptr.regexp)?;
+                out.extend_from_slice(naptr.replacement.as_wire());
+            }
+            RData::HINFO(hinfo) => {
+                string(&mut out, &hinfo.cpu)?;
+                string(&mut out, &hinfo.os)?;
+            }
+            RData::Unknown { data, .. } => out.extend_from_slice(data),
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name(s: &str) -> Name {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn rdata_round_trips() {
+        let all = [
+            RData::A(Ipv4Addr::new(192, 0, 2, 1)),
+            RData::AAAA("2001:db8::1".parse().unwrap()),
+            RData::NS(name("ns1.example.com")),
+            RData::CNAME(name("www.example.com")),
+            RData::PTR(name("host.example.com")),
+            RData::MX(Mx {
+                preference: 10,
+                exchange: name("mail.example.com"),
+            }),
+            RData::TXT(vec![b"v=spf1 -all".to_vec(), vec![]]),
+            RData::SOA(Soa {
+                mname: name("ns1.example.com"),
+                rname: name("hostmaster.example.com"),
+                serial: 2024010101,
+                refresh: 7200,
+                retry: 3600,
+                expire: 1209600,
+                minimum: 300,
+            }),
+            RData::SRV(Srv {
+                priority: 0,
+                weight: 5,
+                port: 25565,
+                target: name("mc.example.com"),
+            }),
+            RData::CAA(Caa {
+                flags: 128,
+                tag: "issue".to_string(),
+                value: b"letsencrypt.org".to_vec(),
+            }),
+            RData::NAPTR(Naptr {
+                order: 100,
+                preference: 10,
+                flags: b"S".to_vec(),
+                services: b"SIP+D2U".to_vec(),
+                regexp: vec![],
+                replacement: name("_sip._udp.example.com"),
+            }),
+            RData::HINFO(Hinfo {
+                cpu: b"RISCV".to_vec(),
+                os: b"LINUX".to_vec(),
+            }),
+            RData::DNAME(name("example.net")),
+            RData::Unknown {
+                rtype: RecordType::Unknown(65534),
+                data: vec![1, 2, 3],
+            },
+        ];
+        for data in all {
+            let bytes = data.encode().unwrap();
+            assert_eq!(RData::decode(data.rtype(), &bytes).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn rdata_rejects_malformed_input() {
+        assert!(matches!(
+            RData::decode(RecordType::A, &[1, 2, 3]),
+            Err(DnsError::MalformedRdata(RecordType::A))
+        ));
+        assert!(RData::decode(RecordType::A, &[1, 2, 3, 4, 5]).is_err());
+        assert!(RData::decode(RecordType::TXT, &[]).is_err());
+        assert!(RData::decode(RecordType::TXT, &[4, b'a']).is_err());
+        assert!(RData::decode(RecordType::MX, b"\x00\x0a\x04mail").is_err());
+        assert!(RData::decode(RecordType::CAA, b"\x00\x00").is_err());
+        assert!(RData::TXT(vec![vec![0; 256]]).encode().is_err());
+    }
+}
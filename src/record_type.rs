@@ -0,0 +1,321 @@
+use std::{fmt, str::FromStr};
+
+use crate::dns_error::DnsError;
+
+macro_rules! record_types {
+    ($($(#[$doc:meta])* $variant:ident = $value:literal, $mnemonic:literal;)*) => {
+        /// The TYPE of a resource record or the QTYPE of a question.
+        ///
+        /// Covers the IANA "Resource Record (RR) TYPEs" registry; values
+        /// without a name there are kept as [`RecordType::Unknown`] and
+        /// written as `TYPEnnn` (RFC 3597).
+        ///
+        /// # Example
+        ///
+        /// ```
+        /// use dns::RecordType;
+        ///
+        /// let srv: RecordType = "srv".parse().unwrap();
+        /// assert_eq!(srv, RecordType::SRV);
+        /// assert_eq!(u16::from(srv), 33);
+        ///
+        /// let private: RecordType = "TYPE65534".parse().unwrap();
+        /// assert_eq!(private, RecordType::Unknown(65534));
+        /// assert_eq!(private.to_string(), "TYPE65534");
+        /// ```
+        #[allow(clippy::upper_case_acronyms)]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum RecordType {
+            $($(#[$doc])* $variant,)*
+            /// A type without a mnemonic.
+            Unknown(u16),
+        }
+        impl From<u16> for RecordType {
+            fn from(value: u16) -> Self {
+                match value {
+                    $($value => RecordType::$variant,)*
+                    _ => RecordType::Unknown(value),
+                }
+            }
+        }
+        impl From<RecordType> for u16 {
+            fn from(value: RecordType) -> Self {
+                match value {
+                    $(RecordType::$variant => $value,)*
+                    RecordType::Unknown(value) => value,
+                }
+            }
+        }
+        impl RecordType {
+            /// # Returns the mnemonic, `None` for [`RecordType::Unknown`]
+            pub fn mnemonic(&self) -> Option<&'static str> {
+                match self {
+                    $(RecordType::$variant => Some($mnemonic),)*
+                    RecordType::Unknown(_) => None,
+                }
+            }
+
+            fn from_mnemonic(s: &str) -> Option<RecordType> {
+                $(if s.eq_ignore_ascii_case($mnemonic) {
+                    return Some(RecordType::$variant);
+                })*
+                None
+            }
+        }
+    };
+}
+
+record_types! {
+    /// a host address
+    A = 1, "A";
+    /// an authoritative name server
+    NS = 2, "NS";
+    /// a mail destination (obsolete, use MX)
+    MD = 3, "MD";
+    /// a mail forwarder (obsolete, use MX)
+    MF = 4, "MF";
+    /// the canonical name for an alias
+    CNAME = 5, "CNAME";
+    /// marks the start of a zone of authority
+    SOA = 6, "SOA";
+    /// a mailbox domain name
+    MB = 7, "MB";
+    /// a mail group member
+    MG = 8, "MG";
+    /// a mail rename domain name
+    MR = 9, "MR";
+    /// a null RR
+    NULL = 10, "NULL";
+    /// a well known service description
+    WKS = 11, "WKS";
+    /// a domain name pointer
+    PTR = 12, "PTR";
+    /// host information
+    HINFO = 13, "HINFO";
+    /// mailbox or mail list information
+    MINFO = 14, "MINFO";
+    /// mail exchange
+    MX = 15, "MX";
+    /// text strings
+    TXT = 16, "TXT";
+    /// responsible person
+    RP = 17, "RP";
+    /// AFS data base location
+    AFSDB = 18, "AFSDB";
+    /// X.25 PSDN address
+    X25 = 19, "X25";
+    /// ISDN address
+    ISDN = 20, "ISDN";
+    /// route through
+    RT = 21, "RT";
+    /// NSAP address
+    NSAP = 22, "NSAP";
+    /// domain name pointer, NSAP style
+    NSAPPTR = 23, "NSAP-PTR";
+    /// security signature
+    SIG = 24, "SIG";
+    /// security key
+    KEY = 25, "KEY";
+    /// X.400 mail mapping information
+    PX = 26, "PX";
+    /// geographical position
+    GPOS = 27, "GPOS";
+    /// IPv6 address
+    AAAA = 28, "AAAA";
+    /// location information
+    LOC = 29, "LOC";
+    /// next domain (obsolete)
+    NXT = 30, "NXT";
+    /// endpoint identifier
+    EID = 31, "EID";
+    /// nimrod locator
+    NIMLOC = 32, "NIMLOC";
+    /// server selection
+    SRV = 33, "SRV";
+    /// ATM address
+    ATMA = 34, "ATMA";
+    /// naming authority pointer
+    NAPTR = 35, "NAPTR";
+    /// key exchanger
+    KX = 36, "KX";
+    /// certificate
+    CERT = 37, "CERT";
+    /// IPv6 address (obsolete, use AAAA)
+    A6 = 38, "A6";
+    /// delegation name
+    DNAME = 39, "DNAME";
+    /// kitchen sink
+    SINK = 40, "SINK";
+    /// EDNS option pseudo record
+    OPT = 41, "OPT";
+    /// address prefix list
+    APL = 42, "APL";
+    /// delegation signer
+    DS = 43, "DS";
+    /// SSH key fingerprint
+    SSHFP = 44, "SSHFP";
+    /// IPsec key
+    IPSECKEY = 45, "IPSECKEY";
+    /// DNSSEC signature
+    RRSIG = 46, "RRSIG";
+    /// next secure record
+    NSEC = 47, "NSEC";
+    /// DNS key
+    DNSKEY = 48, "DNSKEY";
+    /// DHCP identifier
+    DHCID = 49, "DHCID";
+    /// hashed next secure record
+    NSEC3 = 50, "NSEC3";
+    /// NSEC3 parameters
+    NSEC3PARAM = 51, "NSEC3PARAM";
+    /// TLS certificate association
+    TLSA = 52, "TLSA";
+    /// S/MIME certificate association
+    SMIMEA = 53, "SMIMEA";
+    /// host identity protocol
+    HIP = 55, "HIP";
+    /// zone status information
+    NINFO = 56, "NINFO";
+    /// resource key
+    RKEY = 57, "RKEY";
+    /// trust anchor link
+    TALINK = 58, "TALINK";
+    /// child DS
+    CDS = 59, "CDS";
+    /// child DNSKEY
+    CDNSKEY = 60, "CDNSKEY";
+    /// OpenPGP key
+    OPENPGPKEY = 61, "OPENPGPKEY";
+    /// child-to-parent synchronization
+    CSYNC = 62, "CSYNC";
+    /// message digest for DNS zones
+    ZONEMD = 63, "ZONEMD";
+    /// general purpose service binding
+    SVCB = 64, "SVCB";
+    /// service binding for HTTPS
+    HTTPS = 65, "HTTPS";
+    /// endpoint discovery for delegation synchronization
+    DSYNC = 66, "DSYNC";
+    /// hierarchical host identity tag
+    HHIT = 67, "HHIT";
+    /// UAS broadcast remote identification
+    BRID = 68, "BRID";
+    /// sender policy framework
+    SPF = 99, "SPF";
+    /// reserved
+    UINFO = 100, "UINFO";
+    /// reserved
+    UID = 101, "UID";
+    /// reserved
+    GID = 102, "GID";
+    /// reserved
+    UNSPEC = 103, "UNSPEC";
+    /// node identifier
+    NID = 104, "NID";
+    /// 32 bit locator
+    L32 = 105, "L32";
+    /// 64 bit locator
+    L64 = 106, "L64";
+    /// locator FQDN
+    LP = 107, "LP";
+    /// EUI-48 address
+    EUI48 = 108, "EUI48";
+    /// EUI-64 address
+    EUI64 = 109, "EUI64";
+    /// NXDOMAIN indicator for compact denial of existence
+    NXNAME = 128, "NXNAME";
+    /// transaction key
+    TKEY = 249, "TKEY";
+    /// transaction signature
+    TSIG = 250, "TSIG";
+    /// incremental zone transfer
+    IXFR = 251, "IXFR";
+    /// transfer of an entire zone
+    AXFR = 252, "AXFR";
+    /// mailbox related records (MB, MG or MR)
+    MAILB = 253, "MAILB";
+    /// mail agent records (obsolete, use MX)
+    MAILA = 254, "MAILA";
+    /// all records, also written as `*`
+    ANY = 255, "ANY";
+    /// uniform resource identifier
+    URI = 256, "URI";
+    /// certification authority restriction
+    CAA = 257, "CAA";
+    /// application visibility and control
+    AVC = 258, "AVC";
+    /// digital object architecture
+    DOA = 259, "DOA";
+    /// automatic multicast tunneling relay
+    AMTRELAY = 260, "AMTRELAY";
+    /// resolver information
+    RESINFO = 261, "RESINFO";
+    /// public wallet address
+    WALLET = 262, "WALLET";
+    /// BP convergence layer adapter
+    CLA = 263, "CLA";
+    /// BP node number
+    IPN = 264, "IPN";
+    /// DNSSEC trust authorities
+    TA = 32768, "TA";
+    /// DNSSEC lookaside validation (obsolete)
+    DLV = 32769, "DLV";
+}
+
+/// # Parses a type mnemonic
+///
+/// Mnemonics are case-insensitive, `*` is accepted for ANY and `TYPEnnn`
+/// for any numeric type.
+impl FromStr for RecordType {
+    type Err = DnsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "*" {
+            return Ok(RecordType::ANY);
+        }
+        if let Some(rtype) = RecordType::from_mnemonic(s) {
+            return Ok(rtype);
+        }
+        s.get(..4)
+            .filter(|prefix| prefix.eq_ignore_ascii_case("TYPE"))
+            .map(|_| &s[4..])
+            .filter(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+            .and_then(|n| n.parse::<u16>().ok())
+            .map(RecordType::from)
+            .ok_or_else(|| DnsError::InvalidRecordType(s.to_string()))
+    }
+}
+
+impl fmt::Display for RecordType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.mnemonic() {
+            Some(mnemonic) => f.write_str(mnemonic),
+            None => write!(f, "TYPE{}", u16::from(*self)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_type_round_trips_every_value() {
+        for value in 0..=u16::MAX {
+            let rtype = RecordType::from(value);
+            assert_eq!(u16::from(rtype), value);
+            assert_eq!(rtype.to_string().parse::<RecordType>().unwrap(), rtype);
+        }
+    }
+
+    #[test]
+    fn record_type_parses_mnemonics() {
+        assert_eq!("aaaa".parse::<RecordType>().unwrap(), RecordType::AAAA);
+        assert_eq!("NSAP-PTR".parse::<RecordType>().unwrap(), RecordType::NSAPPTR);
+        assert_eq!("*".parse::<RecordType>().unwrap(), RecordType::ANY);
+        assert_eq!("TYPE33".parse::<RecordType>().unwrap(), RecordType::SRV);
+        for text in ["", "AAA", "TYPE", "TYPE65536", "TYPE-1", "TYPE+33"] {
+            assert!(text.parse::<RecordType>().is_err(), "{text} parsed");
+        }
+    }
+}
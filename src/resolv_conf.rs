@@ -0,0 +1,270 @@
+use std::{
+    fs,
+    net::{SocketAddr, SocketAddrV6},
+    path::Path,
+    time::Duration,
+};
+
+use crate::{dns_error::DnsError, Name};
+
+/// The path of the system resolver configuration.
+pub const RESOLV_CONF_PATH: &str = "/etc/resolv.conf";
+
+/// The most name servers the system resolver uses (MAXNS).
+const MAX_NAMESERVERS: usize = 3;
+
+/// The system resolver configuration, as read from `resolv.conf(5)`.
+///
+/// The `search` list, `domain` and `ndots` apply to names that are looked
+/// up with a [`crate::Resolver`], see [`crate::ClientConfig::candidate_names()`].
+/// [`crate::Client::send()`] and [`crate::Message::send()`] send the question
+/// as it is, like `res_send(3)`.
+///
+/// # Example
+///
+/// ```
+/// use dns::ResolvConf;
+///
+/// let conf = ResolvConf::parse("
+/// nameserver 10.0.0.53
+/// search corp.example.com example.com
+/// options ndots:2 rotate edns0
+/// ");
+/// assert_eq!(conf.nameservers, vec!["10.0.0.53:53".parse::<std::net::SocketAddr>().unwrap()]);
+/// assert_eq!(conf.search_list().len(), 2);
+/// assert_eq!(conf.ndots, 2);
+/// assert!(conf.rotate && conf.edns0);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvConf {
+    /// the `nameserver` lines on port 53, at most three are used.
+    /// Link-local IPv6 addresses keep the interface of their zone, like
+    /// `fe80::1%eth0`, as the scope ID.
+    pub nameservers: Vec<SocketAddr>,
+    /// the `search` list.
+    pub search: Vec<Name>,
+    /// the `domain` line.
+    pub domain: Option<Name>,
+    /// names with at least this many dots are tried as given first.
+    pub ndots: u8,
+    /// how long to wait for a server before trying the next one.
+    pub timeout: Duration,
+    /// how many times all servers are tried.
+    pub attempts: u8,
+    /// spread the queries over all servers instead of always starting with
+    /// the first.
+    pub rotate: bool,
+    /// add an EDNS(0) OPT record to queries.
+    pub edns0: bool,
+    /// send all queries over TCP.
+    pub use_vc: bool,
+}
+impl ResolvConf {
+    /// # Creates a new ResolvConf
+    ///
+    /// # Note
+    ///
+    /// This method creates a new ResolvConf with the defaults of the system
+    /// resolver:
+    ///
+    /// - nameservers: Vec::new()
+    /// - search: Vec::new()
+    /// - domain: None
+    /// - ndots: 1
+    /// - timeout: 5 seconds
+    /// - attempts: 2
+    /// - rotate: false
+    /// - edns0: false
+    /// - use_vc: false
+    pub fn new() -> ResolvConf {
+        ResolvConf {
+            nameservers: vec![],
+            search: vec![],
+            domain: None,
+            ndots: 1,
+            timeout: Duration::from_secs(5),
+            attempts: 2,
+            rotate: false,
+            edns0: false,
+            use_vc: false,
+        }
+    }
+
+    /// # Reads the system configuration from `/etc/resolv.conf`
+    pub fn system() -> Result<ResolvConf, DnsError> {
+        ResolvConf::load(RESOLV_CONF_PATH)
+    }
+
+    /// # Reads the configuration from a file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<ResolvConf, DnsError> {
+        let text = fs::read_to_string(path).map_err(DnsError::ConfigError)?;
+        Ok(ResolvConf::parse(&text))
+    }
+
+    /// # Parses the contents of a resolv.conf file
+    ///
+    /// # Note
+    ///
+    /// Like the system resolver, lines and values that are not understood
+    /// are ignored, `domain` and `search` replace each other with the last
+    /// one winning, and the option values are capped at ndots 15, timeout
+    /// 30 seconds and 5 attempts.  The zone of an IPv6 address is either
+    /// the index or the name of an interface, name servers with a zone
+    /// that names no interface of this host are skipped.
+    pub fn parse(text: &str) -> ResolvConf {
+        let mut conf = ResolvConf::new();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("nameserver") => {
+                    if let Some(addr) = words.next().and_then(nameserver) {
+                        if conf.nameservers.len() < MAX_NAMESERVERS {
+                            conf.nameservers.push(addr);
+                        }
+                    }
+                }
+                Some("domain") => {
+                    conf.domain = words.next().and_then(|w| w.parse().ok());
+                    conf.search.clear();
+                }
+                Some("search") => {
+                    conf.search = words.filter_map(|w| w.parse().ok()).collect();
+                    conf.domain = None;
+                }
+                Some("options") => {
+                    for option in words {
+                        conf.set_option(option);
+                    }
+                }
+                _ => {}
+            }
+        }
+        conf
+    }
+
+    fn set_option(&mut self, option: &str) {
+        let (name, value) = match option.split_once(':') {
+            Some((name, value)) => (name, value.parse::<u64>().ok()),
+            None => (option, None),
+        };
+        match (name, value) {
+            ("ndots", Some(n)) => self.ndots = n.min(15) as u8,
+            ("timeout", Some(n)) => self.timeout = Duration::from_secs(n.clamp(1, 30)),
+            ("attempts", Some(n)) => self.attempts = n.clamp(1, 5) as u8,
+            ("rotate", _) => self.rotate = true,
+            ("edns0", _) => self.edns0 = true,
+            ("use-vc", _) => self.use_vc = true,
+            _ => {}
+        }
+    }
+
+    /// # Returns the domains appended to names with too few dots
+    ///
+    /// This is the `search` list, or the `domain` if there is no search list.
+    pub fn search_list(&self) -> Vec<Name> {
+        match &self.domain {
+            Some(domain) if self.search.is_empty() => vec![domain.clone()],
+            _ => self.search.clone(),
+        }
+    }
+}
+
+/// Parses the address of a `nameserver` line, with the zone of a link-local
+/// IPv6 address like `fe80::1%eth0`.
+fn nameserver(word: &str) -> Option<SocketAddr> {
+    let Some((addr, zone)) = word.split_once('%') else {
+        return Some(SocketAddr::new(word.parse().ok()?, 53));
+    };
+    let addr = addr.parse().ok()?;
+    Some(SocketAddr::V6(SocketAddrV6::new(addr, 53, 0, interface_index(zone)?)))
+}
+
+/// Returns the index of the interface a zone stands for.
+#[cfg(unix)]
+fn interface_index(zone: &str) -> Option<u32> {
+    if let Ok(index) = zone.parse() {
+        return Some(index);
+    }
+    let name = std::ffi::CString::new(zone).ok()?;
+    // SAFETY: `name` is a NUL terminated string that outlives the call
+    let index = unsafe { libc::if_nametoindex(name.as_ptr()) };
+    (index != 0).then_some(index)
+}
+
+/// Returns the index of the interface a zone stands for.
+#[cfg(not(unix))]
+fn interface_index(zone: &str) -> Option<u32> {
+    zone.parse().ok()
+}
+
+impl Default for ResolvConf {
+    fn default() -> Self {
+        ResolvConf::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolv_conf_parses_all_keywords() {
+        let conf = ResolvConf::parse(
+            "# generated by NetworkManager
+            domain old.example
+            search corp.example.com example.com
+            nameserver 10.0.0.1
+            nameserver fe80::1%1
+            ; nameserver 10.0.0.9
+            nameserver not-an-address
+            nameserver 10.0.0.2
+            nameserver 10.0.0.3
+            options ndots:20 timeout:3 attempts:0 rotate edns0 single-request use-vc
+            sortlist 10.0.0.0/8",
+        );
+        assert_eq!(
+            conf.nameservers,
+            vec![
+                "10.0.0.1:53".parse::<SocketAddr>().unwrap(),
+                "[fe80::1%1]:53".parse().unwrap(),
+                "10.0.0.2:53".parse().unwrap(),
+            ]
+        );
+        assert_eq!(conf.domain, None);
+        assert_eq!(conf.search_list(), vec!["corp.example.com".parse().unwrap(), "example.com".parse().unwrap()]);
+        assert_eq!(conf.ndots, 15);
+        assert_eq!(conf.timeout, Duration::from_secs(3));
+        assert_eq!(conf.attempts, 1);
+        assert!(conf.rotate && conf.edns0 && conf.use_vc);
+    }
+
+    #[test]
+    fn resolv_conf_keeps_the_zone_of_link_local_servers() {
+        let conf = ResolvConf::parse("nameserver fe80::53%2\nnameserver fe80::1%no-such-interface\nnameserver 10.0.0.1%2\n");
+        let SocketAddr::V6(addr) = conf.nameservers[0] else { panic!("not IPv6") };
+        assert_eq!((addr.ip().to_string(), addr.port(), addr.scope_id()), ("fe80::53".to_string(), 53, 2));
+        assert_eq!(conf.nameservers.len(), 1);
+
+        #[cfg(target_os = "linux")]
+        assert!(nameserver("fe80::1%lo").is_some_and(|addr| matches!(addr, SocketAddr::V6(addr) if addr.scope_id() != 0)));
+    }
+
+    #[test]
+    fn resolv_conf_domain_replaces_search() {
+        let conf = ResolvConf::parse("search a.example\ndomain b.example\n");
+        assert_eq!(conf.search_list(), vec!["b.example".parse().unwrap()]);
+        assert_eq!(ResolvConf::parse(""), ResolvConf::new());
+    }
+
+    #[test]
+    fn resolv_conf_load_reports_missing_files() {
+        assert!(matches!(
+            ResolvConf::load("/nonexistent/resolv.conf"),
+            Err(DnsError::ConfigError(_))
+        ));
+    }
+}
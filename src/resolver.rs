@@ -0,0 +1,403 @@
+use std::net::IpAddr;
+
+use crate::{dns_error::DnsError, Chain, Client, ClientConfig, DnsClass, IterativeResolver, Message, Mx, Name, Question, RData, Rcode, RecordType, Resource, Srv};
+
+/// Looks up names and returns the decoded records.
+///
+/// Names without a trailing dot are searched in the search domains of the
+/// [`ClientConfig`] (see [`ClientConfig::candidate_names()`]), the first
+/// candidate that has records is the answer.  CNAME and DNAME aliases are
+/// followed (see [`Chain`]), asking again for the target if a server
+/// stops in the middle of the chain.
+///
+/// # Errors
+///
+/// Every lookup returns [`DnsError::NxDomain`] if the name does not exist
+/// and [`DnsError::NoData`] if it exists without records of the type, for
+/// the first candidate name that exists.  An error rcode other than NXDOMAIN
+/// is returned as [`DnsError::ErrorResponse`].
+///
+/// # Example
+///
+/// ```no_run
+/// use dns::Resolver;
+///
+/// let resolver = Resolver::system();
+/// for ip in resolver.lookup_ip("example.com").unwrap() {
+///     println!("{ip}");
+/// }
+/// for mx in resolver.lookup_mx("example.com").unwrap() {
+///     println!("{} {}", mx.preference, mx.exchange);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Resolver {
+    upstream: Upstream,
+}
+
+/// Where a [`Resolver`] gets its answers from.
+#[derive(Debug)]
+enum Upstream {
+    Recursive(Client),
+    Iterative(IterativeResolver),
+}
+impl Upstream {
+    fn send(&self, message: &Message) -> Result<Message, DnsError> {
+        match self {
+            Upstream::Recursive(client) => client.send(message),
+            Upstream::Iterative(iterative) => iterative.send(message),
+        }
+    }
+}
+
+impl Resolver {
+    /// # Creates a new Resolver that asks the servers of `config`
+    pub fn new(config: ClientConfig) -> Resolver {
+        Resolver::with_client(Client::new(config))
+    }
+
+    /// # Creates a new Resolver with the system configuration
+    ///
+    /// See [`ClientConfig::system()`].
+    pub fn system() -> Resolver {
+        Resolver::new(ClientConfig::system())
+    }
+
+    /// # Creates a new Resolver that sends its queries with `client`
+    pub fn with_client(client: Client) -> Resolver {
+        Resolver {
+            upstream: Upstream::Recursive(client),
+        }
+    }
+
+    /// # Creates a new Resolver that asks the authoritative servers itself
+    ///
+    /// There are no search domains, every name is taken as absolute.
+    pub fn iterative(iterative: IterativeResolver) -> Resolver {
+        Resolver {
+            upstream: Upstream::Iterative(iterative),
+        }
+    }
+
+    /// # Returns the client the queries are sent with
+    ///
+    /// # Returns
+    ///
+    /// returns None for an iterative Resolver.
+    pub fn client(&self) -> Option<&Client> {
+        match &self.upstream {
+            Upstream::Recursive(client) => Some(client),
+            Upstream::Iterative(_) => None,
+        }
+    }
+
+    /// # Looks up the records of any type
+    ///
+    /// # Returns
+    ///
+    /// returns the records of `rtype` in class IN at the end of the alias
+    /// chain of the name.
+    pub fn lookup(&self, name: &str, rtype: RecordType) -> Result<Vec<Resource>, DnsError> {
+        self.search(name, |name| self.query(name, rtype))
+    }
+
+    /// # Looks up the IPv4 and IPv6 addresses of a host
+    ///
+    /// # Returns
+    ///
+    /// returns the addresses of the A records followed by those of the AAAA
+    /// records.  The error is the one of the A query if neither query
+    /// returned an address.
+    pub fn lookup_ip(&self, host: &str) -> Result<Vec<IpAddr>, DnsError> {
+        self.search(host, |name| {
+            let mut ips = vec![];
+            let mut error = None;
+            for rtype in [RecordType::A, RecordType::AAAA] {
+                match self.query(name, rtype) {
+                    Ok(records) => {
+                        for record in records {
+                            match record.data()? {
+                                RData::A(ip) => ips.push(IpAddr::V4(ip)),
+                                RData::AAAA(ip) => ips.push(IpAddr::V6(ip)),
+                                _ => {}
+                            }
+                        }
+                    }
+                    Err(e) => error = error.or(Some(e)),
+                }
+            }
+            match (ips.is_empty(), error) {
+                (true, Some(e)) => Err(e),
+                _ => Ok(ips),
+            }
+        })
+    }
+
+    /// # Looks up the services of a name like `_sip._udp.example.com`
+    ///
+    /// # Returns
+    ///
+    /// returns the SRV records sorted by priority, the weights are left to
+    /// the caller (RFC 2782).
+    pub fn lookup_srv(&self, name: &str) -> Result<Vec<Srv>, DnsError> {
+        let mut services = self.decode(name, RecordType::SRV, |data| match data {
+            RData::SRV(srv) => Some(srv),
+            _ => None,
+        })?;
+        services.sort_by_key(|srv| srv.priority);
+        Ok(services)
+    }
+
+    /// # Looks up the mail exchangers of a domain
+    ///
+    /// # Returns
+    ///
+    /// returns the MX records sorted by preference, most preferred first.
+    pub fn lookup_mx(&self, domain: &str) -> Result<Vec<Mx>, DnsError> {
+        let mut exchanges = self.decode(domain, RecordType::MX, |data| match data {
+            RData::MX(mx) => Some(mx),
+            _ => None,
+        })?;
+        exchanges.sort_by_key(|mx| mx.preference);
+        Ok(exchanges)
+    }
+
+    /// # Looks up the text records of a name
+    ///
+    /// # Returns
+    ///
+    /// returns one string per TXT record, its character strings joined
+    /// without a separator as SPF and DKIM expect.  Invalid UTF-8 is
+    /// replaced, [`Resolver::lookup()`] returns the raw records.
+    pub fn lookup_txt(&self, name: &str) -> Result<Vec<String>, DnsError> {
+        self.decode(name, RecordType::TXT, |data| match data {
+            RData::TXT(strings) => Some(String::from_utf8_lossy(&strings.concat()).into_owned()),
+            _ => None,
+        })
+    }
+
+    /// # Looks up the names of an address
+    ///
+    /// # Returns
+    ///
+    /// returns the names of the PTR records of [`Name::reverse()`], the
+    /// search domains are not used.
+    pub fn reverse_lookup(&self, ip: IpAddr) -> Result<Vec<Name>, DnsError> {
+        let records = self.query(&Name::reverse(ip), RecordType::PTR)?;
+        let mut names = vec![];
+        for record in records {
+            if let RData::PTR(name) = record.data()? {
+                names.push(name);
+            }
+        }
+        Ok(names)
+    }
+}
+impl Resolver {
+    /// Returns the answer of the first candidate name that has one, or the
+    /// NODATA of the first name that exists, else the first NXDOMAIN.
+    fn search<T, F>(&self, name: &str, lookup: F) -> Result<T, DnsError>
+    where
+        F: Fn(&Name) -> Result<T, DnsError>,
+    {
+        let mut negative = None;
+        let names = match &self.upstream {
+            Upstream::Recursive(client) => client.config().candidate_names(name)?,
+            Upstream::Iterative(_) => vec![name.parse()?],
+        };
+        for name in names {
+            match lookup(&name) {
+                Err(e @ DnsError::NoData { .. }) => {
+                    if !matches!(negative, Some(DnsError::NoData { .. })) {
+                        negative = Some(e);
+                    }
+                }
+                Err(e @ DnsError::NxDomain(_)) => negative = negative.or(Some(e)),
+                result => return result,
+            }
+        }
+        Err(negative.expect("there is at least one candidate name"))
+    }
+
+    /// Looks up the records of `rtype` and decodes them with `select`.
+    fn decode<T, F>(&self, name: &str, rtype: RecordType, select: F) -> Result<Vec<T>, DnsError>
+    where
+        F: Fn(RData) -> Option<T>,
+    {
+        let mut values = vec![];
+        for record in self.lookup(name, rtype)? {
+            values.extend(select(record.data()?));
+        }
+        Ok(values)
+    }
+
+    /// Asks for the records of `rtype` at exactly `name`, following the
+    /// aliases on the way.
+    ///
+    /// A server that stops in the middle of a chain without proving that
+    /// its target has no records, with the SOA of a negative answer, is
+    /// asked again for the target.
+    fn query(&self, name: &Name, rtype: RecordType) -> Result<Vec<Resource>, DnsError> {
+        let mut chain = Chain::new(name.clone());
+        loop {
+            let response = self.upstream.send(&query(&chain.target, rtype))?;
+            let rcode = response.rcode();
+            if !matches!(rcode, Rcode::NoError | Rcode::NXDomain) {
+                return Err(DnsError::ErrorResponse(rcode));
+            }
+            let followed = chain.aliases.len();
+            chain.follow(&response.answer, rtype, DnsClass::IN)?;
+            if chain.is_complete() {
+                return Ok(chain.records);
+            }
+            if rcode == Rcode::NXDomain {
+                return Err(DnsError::NxDomain(chain.target));
+            }
+            let negative = response.authority.iter().any(|r| r.rtype == RecordType::SOA);
+            if chain.aliases.len() == followed || negative {
+                return Err(DnsError::NoData { name: chain.target, rtype });
+            }
+        }
+    }
+}
+impl Default for Resolver {
+    fn default() -> Self {
+        Resolver::new(ClientConfig::new())
+    }
+}
+
+/// Builds a recursive query for the records of `rtype` at `name`.
+fn query(name: &Name, rtype: RecordType) -> Message {
+    let mut message = Message::new();
+    message.header.set_rd(true);
+    let mut question = Question {
+        qname: name.clone(),
+        ..Question::new()
+    };
+    question.set_qtype(rtype);
+    message.question.push(question);
+    message.header.qdcount = 1;
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Transport;
+    use std::{net::SocketAddr, time::Duration};
+
+    /// Answers from a fixed set of records like an authoritative server.
+    struct Zone {
+        records: Vec<Resource>,
+    }
+    impl Zone {
+        fn new(records: Vec<(&str, RData)>) -> Zone {
+            let records = records
+                .into_iter()
+                .map(|(name, data)| Resource::new(name.parse().unwrap(), 300, data).unwrap())
+                .collect();
+            Zone { records }
+        }
+    }
+    impl Transport for Zone {
+        fn send(&self, _server: SocketAddr, query: &Message, _timeout: Duration) -> Result<Message, DnsError> {
+            let mut response = query.clone();
+            response.header.set_qr(true);
+            let question = &query.question[0];
+            let owned: Vec<&Resource> = self.records.iter().filter(|r| r.name == question.qname).collect();
+            if owned.is_empty() {
+                response.header.set_rcode(Rcode::NXDomain);
+            }
+            let mut matching: Vec<&Resource> = owned.iter().copied().filter(|r| r.rtype == question.qtype()).collect();
+            if matching.is_empty() {
+                // an alias to a name the server leaves to the resolver
+                matching = owned.into_iter().filter(|r| r.rtype == RecordType::CNAME).collect();
+            }
+            response.answer.extend(matching.into_iter().cloned());
+            Ok(response)
+        }
+    }
+
+    fn resolver(zone: Zone) -> Resolver {
+        let mut config = ClientConfig::new();
+        config.set_search(vec!["example.com".parse().unwrap()]);
+        Resolver::with_client(Client::with_transport(config, zone))
+    }
+
+    #[test]
+    fn resolver_decodes_records() {
+        let resolver = resolver(Zone::new(vec![
+            ("www.example.com", RData::A([192, 0, 2, 1].into())),
+            ("www.example.com", RData::AAAA("2001:db8::1".parse().unwrap())),
+            ("example.com", RData::MX(Mx { preference: 20, exchange: "mx2.example.com".parse().unwrap() })),
+            ("example.com", RData::MX(Mx { preference: 10, exchange: "mx1.example.com".parse().unwrap() })),
+            ("example.com", RData::TXT(vec![b"v=spf1 ".to_vec(), b"-all".to_vec()])),
+            ("1.2.0.192.in-addr.arpa", RData::PTR("www.example.com".parse().unwrap())),
+        ]));
+
+        let ips = resolver.lookup_ip("www.example.com").unwrap();
+        assert_eq!(ips, vec!["192.0.2.1".parse::<IpAddr>().unwrap(), "2001:db8::1".parse().unwrap()]);
+        let exchanges: Vec<u16> = resolver.lookup_mx("example.com").unwrap().iter().map(|mx| mx.preference).collect();
+        assert_eq!(exchanges, vec![10, 20]);
+        assert_eq!(resolver.lookup_txt("example.com").unwrap(), vec!["v=spf1 -all"]);
+        let names = resolver.reverse_lookup("192.0.2.1".parse().unwrap()).unwrap();
+        assert_eq!(names, vec!["www.example.com".parse::<Name>().unwrap()]);
+    }
+
+    #[test]
+    fn resolver_sorts_services_by_priority() {
+        let srv = |priority, target: &str| {
+            RData::SRV(Srv {
+                priority,
+                weight: 0,
+                port: 5060,
+                target: target.parse().unwrap(),
+            })
+        };
+        let resolver = resolver(Zone::new(vec![
+            ("_sip._udp.example.com", srv(2, "backup.example.com")),
+            ("_sip._udp.example.com", srv(1, "primary.example.com")),
+        ]));
+        let targets: Vec<String> =
+            resolver.lookup_srv("_sip._udp.example.com").unwrap().iter().map(|srv| srv.target.to_string()).collect();
+        assert_eq!(targets, vec!["primary.example.com", "backup.example.com"]);
+    }
+
+    #[test]
+    fn resolver_tells_nxdomain_from_nodata() {
+        let resolver = resolver(Zone::new(vec![("www.example.com", RData::A([192, 0, 2, 1].into()))]));
+
+        // found in the search domain
+        assert_eq!(resolver.lookup_ip("www").unwrap().len(), 1);
+        assert!(matches!(
+            resolver.lookup_mx("www.example.com."),
+            Err(DnsError::NoData { rtype: RecordType::MX, .. })
+        ));
+        match resolver.lookup_ip("missing.example.com.") {
+            Err(DnsError::NxDomain(name)) => assert_eq!(name.to_string(), "missing.example.com"),
+            other => panic!("expected NXDOMAIN, got {other:?}"),
+        }
+        // the name exists in the search domain, but has no TXT records
+        assert!(matches!(resolver.lookup_txt("www"), Err(DnsError::NoData { .. })));
+    }
+
+    #[test]
+    fn resolver_follows_aliases_across_queries() {
+        let resolver = resolver(Zone::new(vec![
+            ("alias.example.com", RData::CNAME("www.example.com".parse().unwrap())),
+            ("www.example.com", RData::A([192, 0, 2, 1].into())),
+            ("dangling.example.com", RData::CNAME("gone.example.com".parse().unwrap())),
+            ("loop.example.com", RData::CNAME("loop.example.com".parse().unwrap())),
+        ]));
+
+        assert_eq!(resolver.lookup_ip("alias.example.com.").unwrap(), vec!["192.0.2.1".parse::<IpAddr>().unwrap()]);
+        let records = resolver.lookup("alias.example.com.", RecordType::CNAME).unwrap();
+        assert_eq!(records[0].data().unwrap(), RData::CNAME("www.example.com".parse().unwrap()));
+        // the error names the end of the chain
+        match resolver.lookup_ip("dangling.example.com.") {
+            Err(DnsError::NxDomain(name)) => assert_eq!(name.to_string(), "gone.example.com"),
+            other => panic!("expected NXDOMAIN, got {other:?}"),
+        }
+        assert!(matches!(resolver.lookup_ip("loop.example.com."), Err(DnsError::AliasLoop(_))));
+    }
+}
@@ -0,0 +1,449 @@
+use std::{
+    collections::HashMap,
+    io::{self, Read, Write},
+    net::{Shutdown, SocketAddr, TcpStream},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{dns_error::DnsError, EdnsOption, Message};
+
+/// Writes a message with the two octet length prefix of RFC 1035 4.2.2.
+///
+/// The prefix and the message go out in one write, some servers do not
+/// cope with the length arriving in a segment of its own (RFC 7766 8).
+pub(crate) fn write_frame<W: Write>(stream: &mut W, data: &[u8]) -> io::Result<()> {
+    let len = u16::try_from(data.len()).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "message too long"))?;
+    let mut frame = Vec::with_capacity(data.len() + 2);
+    frame.extend_from_slice(&len.to_be_bytes());
+    frame.extend_from_slice(data);
+    stream.write_all(&frame)?;
+    stream.flush()
+}
+
+/// Reads one length prefixed message.
+pub(crate) fn read_frame<R: Read>(stream: &mut R) -> io::Result<Vec<u8>> {
+    let mut len = [0; 2];
+    stream.read_exact(&mut len)?;
+    let mut buf = vec![0; u16::from_be_bytes(len) as usize];
+    stream.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+/// Maps an I/O error of `server`, turning the platform dependent timeout
+/// kinds into [`DnsError::Timeout`].
+pub(crate) fn io_error(e: io::Error, server: SocketAddr, timeout: Duration) -> DnsError {
+    match e.kind() {
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => DnsError::Timeout { server, timeout },
+        _ => DnsError::TcpSocketError(e),
+    }
+}
+
+/// A TCP connection to one server that is kept open between queries.
+///
+/// Queries are pipelined (RFC 7766 6.2.1.1): they are written as soon as
+/// they are sent, without waiting for earlier replies, and the replies are
+/// matched to their queries by ID and question in whatever order they
+/// arrive.  Queries that share an ID are sent with a fresh one, the reply
+/// carries the ID of the query again.
+///
+/// The connection is opened by the first query and closed once it was idle
+/// for the idle timeout.  A server may change that timeout with the
+/// edns-tcp-keepalive option (RFC 7828), which is asked for in every query
+/// that carries an OPT record.  A connection the server closed is replaced
+/// by a new one on the next query.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use dns::{Message, TcpClient};
+///
+/// let client = TcpClient::new("10.0.0.53:53".parse().unwrap(), Duration::from_secs(5));
+///
+/// let mut queries = vec![];
+/// for name in ["example.com", "example.net", "example.org"] {
+///     let mut message = Message::new();
+///     message.set_questions(vec![name.to_string()]).unwrap();
+///     queries.push(message);
+/// }
+/// for response in client.send_all(&queries).unwrap() {
+///     println!("{:?}", response.unwrap().answer);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct TcpClient {
+    server: SocketAddr,
+    timeout: Duration,
+    idle_timeout: Duration,
+    connection: Mutex<Option<Arc<Connection>>>,
+}
+
+#[derive(Debug)]
+struct Connection {
+    writer: Mutex<TcpStream>,
+    pending: Mutex<HashMap<u16, Pending>>,
+    state: Mutex<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    closed: bool,
+    idle_timeout: Duration,
+    last_used: Instant,
+}
+
+/// A query that waits for its reply.
+#[derive(Debug)]
+struct Pending {
+    query: Message,
+    reply: mpsc::Sender<Message>,
+}
+
+/// A query that was written, see [`Connection::submit()`].
+struct Ticket {
+    id: u16,
+    wire_id: u16,
+    reply: mpsc::Receiver<Message>,
+}
+
+impl TcpClient {
+    /// # Creates a new TcpClient
+    ///
+    /// # Note
+    ///
+    /// No connection is opened until the first query.  The idle timeout is
+    /// 10 seconds until the server announces another one.
+    pub fn new(server: SocketAddr, timeout: Duration) -> TcpClient {
+        TcpClient {
+            server,
+            timeout,
+            idle_timeout: Duration::from_secs(10),
+            connection: Mutex::new(None),
+        }
+    }
+
+    /// # Returns the server
+    pub fn server(&self) -> SocketAddr {
+        self.server
+    }
+
+    /// # Sets how long an unused connection is kept open
+    pub fn set_idle_timeout(&mut self, idle_timeout: Duration) {
+        self.idle_timeout = idle_timeout;
+    }
+
+    /// # Sends one message and waits for its reply
+    pub fn send(&self, message: &Message) -> Result<Message, DnsError> {
+        self.exchange(std::slice::from_ref(message), self.timeout)?.remove(0)
+    }
+
+    /// # Sends all messages at once and waits for their replies
+    ///
+    /// # Returns
+    ///
+    /// returns the reply or the error for every message, in the order of
+    /// the messages, or an error if the server could not be reached.
+    pub fn send_all(&self, messages: &[Message]) -> Result<Vec<Result<Message, DnsError>>, DnsError> {
+        self.exchange(messages, self.timeout)
+    }
+
+    /// Sends the messages, waiting up to `timeout` for all of them.
+    ///
+    /// Queries lost because a reused connection turned out to be closed
+    /// are sent once more on a new connection.
+    pub(crate) fn exchange(
+        &self,
+        messages: &[Message],
+        timeout: Duration,
+    ) -> Result<Vec<Result<Message, DnsError>>, DnsError> {
+        let mut results: Vec<Option<Result<Message, DnsError>>> = messages.iter().map(|_| None).collect();
+        let mut todo: Vec<usize> = (0..messages.len()).collect();
+        let mut retried = false;
+        while !todo.is_empty() {
+            let (connection, reused) = match self.connection(timeout) {
+                Ok(c) => c,
+                Err(e) if !retried => return Err(e),
+                Err(_) => {
+                    for i in todo {
+                        results[i] = Some(Err(DnsError::ConnectionClosed));
+                    }
+                    break;
+                }
+            };
+            let deadline = Instant::now() + timeout;
+            let tickets: Vec<_> = todo.iter().map(|i| connection.submit(&messages[*i], self.server, timeout)).collect();
+            let mut retry = vec![];
+            for (i, ticket) in todo.into_iter().zip(tickets) {
+                let result = ticket.and_then(|t| connection.wait(t, deadline, self.server, timeout));
+                match result {
+                    Err(DnsError::ConnectionClosed) if reused && !retried => retry.push(i),
+                    result => results[i] = Some(result),
+                }
+            }
+            todo = retry;
+            retried = true;
+        }
+        Ok(results.into_iter().map(|r| r.expect("every message has a result")).collect())
+    }
+
+    /// Returns the open connection, or a new one if there is none that can
+    /// be used, and whether it was used before.
+    fn connection(&self, timeout: Duration) -> Result<(Arc<Connection>, bool), DnsError> {
+        let mut slot = self.connection.lock().unwrap();
+        if let Some(connection) = slot.as_ref() {
+            if connection.usable() {
+                return Ok((connection.clone(), true));
+            }
+            connection.shutdown();
+        }
+        let stream = TcpStream::connect_timeout(&self.server, timeout).map_err(|e| io_error(e, self.server, timeout))?;
+        stream.set_nodelay(true).map_err(DnsError::TcpSocketError)?;
+        stream.set_write_timeout(Some(timeout)).map_err(DnsError::TcpSocketError)?;
+        let reader = stream.try_clone().map_err(DnsError::TcpSocketError)?;
+        let connection = Arc::new(Connection {
+            writer: Mutex::new(stream),
+            pending: Mutex::new(HashMap::new()),
+            state: Mutex::new(State {
+                closed: false,
+                idle_timeout: self.idle_timeout,
+                last_used: Instant::now(),
+            }),
+        });
+        let shared = connection.clone();
+        thread::spawn(move || shared.read_loop(reader));
+        *slot = Some(connection.clone());
+        Ok((connection, false))
+    }
+}
+impl Drop for TcpClient {
+    fn drop(&mut self) {
+        if let Some(connection) = self.connection.lock().unwrap().take() {
+            connection.shutdown();
+        }
+    }
+}
+
+impl Connection {
+    /// Returns true if the connection is open and not idle for too long.
+    fn usable(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        !state.closed && (state.last_used.elapsed() < state.idle_timeout || !self.pending.lock().unwrap().is_empty())
+    }
+
+    fn shutdown(&self) {
+        let _ = self.writer.lock().unwrap().shutdown(Shutdown::Both);
+    }
+
+    /// Registers the query under an unused ID and writes it.
+    fn submit(&self, message: &Message, server: SocketAddr, timeout: Duration) -> Result<Ticket, DnsError> {
+        let mut query = message.clone();
+        if let Some(mut edns) = query.edns()? {
+            if !edns.options.iter().any(|o| matches!(o, EdnsOption::TcpKeepalive(_))) {
+                edns.options.push(EdnsOption::TcpKeepalive(None));
+                query.set_edns(edns)?;
+            }
+        }
+        let (reply, receiver) = mpsc::channel();
+        let wire_id = {
+            let mut pending = self.pending.lock().unwrap();
+            if pending.len() > u16::MAX as usize {
+                return Err(DnsError::TooManyPending);
+            }
+            while pending.contains_key(&query.header.id) {
+                query.header.set_id(rand::random());
+            }
+            let data = query.encode()?;
+            if data.len() > u16::MAX as usize {
+                return Err(DnsError::MessageTooLong(data.len()));
+            }
+            let id = query.header.id;
+            pending.insert(id, Pending { query, reply });
+            // written while holding the lock, so the reader can not see
+            // the reply before the query is registered
+            if let Err(e) = write_frame(&mut *self.writer.lock().unwrap(), &data) {
+                pending.remove(&id);
+                return Err(io_error(e, server, timeout));
+            }
+            id
+        };
+        self.state.lock().unwrap().last_used = Instant::now();
+        Ok(Ticket {
+            id: message.header.id,
+            wire_id,
+            reply: receiver,
+        })
+    }
+
+    /// Waits for the reply to a submitted query.
+    fn wait(&self, ticket: Ticket, deadline: Instant, server: SocketAddr, timeout: Duration) -> Result<Message, DnsError> {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        match ticket.reply.recv_timeout(remaining) {
+            Ok(mut response) => {
+                response.header.set_id(ticket.id);
+                Ok(response)
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                self.pending.lock().unwrap().remove(&ticket.wire_id);
+                Err(DnsError::Timeout { server, timeout })
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => Err(DnsError::ConnectionClosed),
+        }
+    }
+
+    /// Hands the replies to the waiting queries until the connection ends.
+    fn read_loop(&self, mut stream: TcpStream) {
+        while let Ok(buf) = read_frame(&mut stream) {
+            // a reply that does not parse can not be matched to its query
+            let Ok(response) = Message::parse(&buf) else { continue };
+            {
+                let mut state = self.state.lock().unwrap();
+                state.last_used = Instant::now();
+                if let Ok(Some(edns)) = response.edns() {
+                    for option in edns.options {
+                        if let EdnsOption::TcpKeepalive(Some(t)) = option {
+                            // in units of 100 milliseconds
+                            state.idle_timeout = Duration::from_millis(t as u64 * 100);
+                        }
+                    }
+                }
+            }
+            let mut pending = self.pending.lock().unwrap();
+            if pending.get(&response.header.id).is_some_and(|p| response.is_response_to(&p.query)) {
+                let waiting = pending.remove(&response.header.id).unwrap();
+                let _ = waiting.reply.send(response);
+            }
+        }
+        self.state.lock().unwrap().closed = true;
+        // dropping the senders wakes the waiting queries
+        self.pending.lock().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Edns, RData, Resource};
+    use std::net::TcpListener;
+
+    #[test]
+    fn frames_carry_a_length_prefix() {
+        let mut out = vec![];
+        write_frame(&mut out, &[1, 2, 3]).unwrap();
+        assert_eq!(out, vec![0, 3, 1, 2, 3]);
+        assert_eq!(read_frame(&mut &out[..]).unwrap(), vec![1, 2, 3]);
+
+        assert!(read_frame(&mut &out[..4]).is_err());
+        assert!(write_frame(&mut vec![], &vec![0; 65536]).is_err());
+    }
+
+    fn query(name: &str) -> Message {
+        let mut message = Message::new();
+        message.set_questions(vec![name.to_string()]).unwrap();
+        message
+    }
+
+    fn answer(query: &Message, keepalive: Option<u16>) -> Vec<u8> {
+        let mut response = query.clone();
+        response.header.set_qr(true);
+        let name = response.question[0].qname.clone();
+        response.answer.push(Resource::new(name, 60, RData::A([192, 0, 2, 1].into())).unwrap());
+        if let Some(t) = keepalive {
+            let mut edns = Edns::new();
+            edns.options.push(EdnsOption::TcpKeepalive(Some(t)));
+            response.set_edns(edns).unwrap();
+        }
+        response.encode().unwrap()
+    }
+
+    #[test]
+    fn tcp_client_matches_out_of_order_replies() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpClient::new(listener.local_addr().unwrap(), Duration::from_secs(5));
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let queries: Vec<Message> = (0..3).map(|_| Message::parse(&read_frame(&mut stream).unwrap()).unwrap()).collect();
+            for query in queries.iter().rev() {
+                write_frame(&mut stream, &answer(query, None)).unwrap();
+            }
+            queries
+        });
+
+        let mut queries = vec![query("a.example"), query("b.example"), query("c.example")];
+        // the same ID twice must still be told apart
+        let id = queries[0].header.id;
+        queries[1].header.set_id(id);
+        let responses = client.send_all(&queries).unwrap();
+        let seen = handle.join().unwrap();
+
+        assert_ne!(seen[0].header.id, seen[1].header.id);
+        for (query, response) in queries.iter().zip(responses) {
+            let response = response.unwrap();
+            assert_eq!(response.header.id, query.header.id);
+            assert_eq!(response.question, query.question);
+        }
+    }
+
+    #[test]
+    fn tcp_client_reuses_the_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpClient::new(listener.local_addr().unwrap(), Duration::from_secs(5));
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            for _ in 0..2 {
+                let query = Message::parse(&read_frame(&mut stream).unwrap()).unwrap();
+                write_frame(&mut stream, &answer(&query, None)).unwrap();
+            }
+        });
+
+        assert!(client.send(&query("a.example")).is_ok());
+        assert!(client.send(&query("b.example")).is_ok());
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn tcp_client_honors_keepalive() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpClient::new(listener.local_addr().unwrap(), Duration::from_secs(5));
+        let handle = thread::spawn(move || {
+            let mut keepalives = vec![];
+            for _ in 0..2 {
+                let (mut stream, _) = listener.accept().unwrap();
+                let query = Message::parse(&read_frame(&mut stream).unwrap()).unwrap();
+                keepalives.push(query.edns().unwrap().unwrap().options);
+                // a timeout of zero asks the client to close the connection
+                write_frame(&mut stream, &answer(&query, Some(0))).unwrap();
+            }
+            keepalives
+        });
+
+        let mut message = query("a.example");
+        message.set_edns(Edns::new()).unwrap();
+        assert!(client.send(&message).is_ok());
+        assert!(client.send(&message).is_ok());
+        let keepalives = handle.join().unwrap();
+        assert_eq!(keepalives[0], vec![EdnsOption::TcpKeepalive(None)]);
+    }
+
+    #[test]
+    fn tcp_client_reconnects_after_the_server_closed() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpClient::new(listener.local_addr().unwrap(), Duration::from_secs(5));
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let query = Message::parse(&read_frame(&mut stream).unwrap()).unwrap();
+            write_frame(&mut stream, &answer(&query, None)).unwrap();
+            // reads the second query, then closes without answering
+            read_frame(&mut stream).unwrap();
+            drop(stream);
+            let (mut stream, _) = listener.accept().unwrap();
+            let query = Message::parse(&read_frame(&mut stream).unwrap()).unwrap();
+            write_frame(&mut stream, &answer(&query, None)).unwrap();
+        });
+
+        assert!(client.send(&query("a.example")).is_ok());
+        assert!(client.send(&query("b.example")).is_ok());
+        handle.join().unwrap();
+    }
+}
@@ -0,0 +1,544 @@
+use std::{
+    io,
+    net::{SocketAddr, TcpStream},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use rustls::{
+    client::{
+        danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+        WebPkiServerVerifier,
+    },
+    crypto::{ring::default_provider, CryptoProvider},
+    pki_types::{CertificateDer, ServerName, UnixTime},
+    ClientConnection, DigitallySignedStruct, RootCertStore, SignatureScheme, StreamOwned,
+};
+
+use crate::{
+    dns_error::DnsError,
+    tcp::{read_frame, write_frame},
+    transport::Pool,
+    Message, Transport,
+};
+
+/// The port of DNS over TLS.
+pub const DOT_PORT: u16 = 853;
+
+/// How a DNS over TLS server is authenticated (RFC 7858 4, RFC 8310).
+///
+/// By default the certificate must chain up to one of the Mozilla root
+/// certificates and be valid for the authentication name, or for the IP
+/// address of the server if no name is set.  The name is also sent as SNI.
+///
+/// With SPKI pins the server is accepted if the SHA-256 hash of the public
+/// key of its own certificate is pinned, the certificate chain is not
+/// checked then (the out-of-band key-pinned profile of RFC 7858 4.2).  The
+/// keys of the CA certificates it presents are not compared, anyone can
+/// send those along with a certificate of their own.
+///
+/// # Example
+///
+/// ```
+/// use dns::TlsConfig;
+///
+/// let mut tls = TlsConfig::new();
+/// tls.set_server_name("one.one.one.one");
+/// assert_eq!(tls.server_name(), Some("one.one.one.one"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsConfig {
+    server_name: Option<String>,
+    default_roots: bool,
+    roots: Vec<Vec<u8>>,
+    pins: Vec<[u8; 32]>,
+}
+impl TlsConfig {
+    /// # Creates a new TlsConfig
+    ///
+    /// # Note
+    ///
+    /// This method creates a new TlsConfig with the following default values:
+    ///
+    /// - server_name: None         // the IP address is authenticated
+    /// - default roots: the Mozilla root certificates
+    /// - pins: Vec::new()
+    pub fn new() -> TlsConfig {
+        TlsConfig {
+            server_name: None,
+            default_roots: true,
+            roots: vec![],
+            pins: vec![],
+        }
+    }
+
+    /// # Sets the authentication name, which is also sent as SNI
+    pub fn set_server_name(&mut self, name: &str) {
+        self.server_name = Some(name.to_string());
+    }
+
+    /// # Returns the authentication name
+    pub fn server_name(&self) -> Option<&str> {
+        self.server_name.as_deref()
+    }
+
+    /// # Trusts a root certificate in addition to the existing ones
+    ///
+    /// # Arguments
+    ///
+    /// takes the certificate in DER format.
+    pub fn add_root_certificate(&mut self, der: &[u8]) {
+        self.roots.push(der.to_vec());
+    }
+
+    /// # Stops trusting any root certificate, including the Mozilla ones
+    pub fn clear_root_certificates(&mut self) {
+        self.default_roots = false;
+        self.roots.clear();
+    }
+
+    /// # Pins the SHA-256 hash of a SubjectPublicKeyInfo
+    ///
+    /// See [`TlsConfig::spki_pin()`] to compute the hash from a certificate.
+    pub fn add_spki_pin(&mut self, sha256: [u8; 32]) {
+        self.pins.push(sha256);
+    }
+
+    /// # Returns the SHA-256 hash of the public key of a certificate
+    ///
+    /// # Arguments
+    ///
+    /// takes the certificate in DER format.
+    pub fn spki_pin(der: &[u8]) -> Result<[u8; 32], DnsError> {
+        let der = CertificateDer::from(der);
+        let cert = webpki::EndEntityCert::try_from(&der)
+            .map_err(|_| tls_error(rustls::Error::InvalidCertificate(rustls::CertificateError::BadEncoding)))?;
+        let digest = ring::digest::digest(&ring::digest::SHA256, cert.subject_public_key_info().as_ref());
+        let mut pin = [0; 32];
+        pin.copy_from_slice(digest.as_ref());
+        Ok(pin)
+    }
+
+    /// Builds the rustls configuration offering the ALPN protocol `alpn`.
+    pub(crate) fn client_config(&self, alpn: &[u8]) -> Result<rustls::ClientConfig, DnsError> {
+        let provider = Arc::new(default_provider());
+        let mut roots = RootCertStore::empty();
+        if self.default_roots {
+            roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        }
+        for der in &self.roots {
+            roots.add(CertificateDer::from(der.clone())).map_err(tls_error)?;
+        }
+        let webpki = if roots.is_empty() {
+            None
+        } else {
+            Some(
+                WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+                    .build()
+                    .map_err(tls_error)?,
+            )
+        };
+        let verifier = Verifier {
+            webpki,
+            pins: self.pins.clone(),
+            provider: provider.clone(),
+        };
+        let mut config = rustls::ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .map_err(tls_error)?
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(verifier))
+            .with_no_client_auth();
+        config.alpn_protocols = vec![alpn.to_vec()];
+        Ok(config)
+    }
+
+    /// Returns the name the certificate of `server` must be valid for.
+    pub(crate) fn name_for(&self, server: SocketAddr) -> Result<ServerName<'static>, DnsError> {
+        match &self.server_name {
+            Some(name) => ServerName::try_from(name.clone()).map_err(tls_error),
+            None => Ok(ServerName::IpAddress(server.ip().into())),
+        }
+    }
+}
+impl Default for TlsConfig {
+    fn default() -> Self {
+        TlsConfig::new()
+    }
+}
+
+pub(crate) fn tls_error<E: Into<Box<dyn std::error::Error + Send + Sync>>>(e: E) -> DnsError {
+    DnsError::TlsError(io::Error::other(e))
+}
+
+/// Checks the certificate against the pins, or the roots if there are none.
+#[derive(Debug)]
+struct Verifier {
+    webpki: Option<Arc<WebPkiServerVerifier>>,
+    pins: Vec<[u8; 32]>,
+    provider: Arc<CryptoProvider>,
+}
+impl ServerCertVerifier for Verifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if !self.pins.is_empty() {
+            // only the certificate the handshake is signed with proves the key
+            let pinned = TlsConfig::spki_pin(end_entity).is_ok_and(|pin| self.pins.contains(&pin));
+            return match pinned {
+                true => Ok(ServerCertVerified::assertion()),
+                false => Err(rustls::Error::General("no pinned public key".to_string())),
+            };
+        }
+        match &self.webpki {
+            Some(webpki) => webpki.verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now),
+            None => Err(rustls::Error::InvalidCertificate(rustls::CertificateError::UnknownIssuer)),
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+type TlsStream = StreamOwned<ClientConnection, TcpStream>;
+
+/// A DNS over TLS connection to one server (RFC 7858).
+///
+/// The connection is opened by the first query and kept open for the next
+/// ones.  Queries on one TlsClient are sent one after another.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use dns::{Message, TlsClient, TlsConfig};
+///
+/// let mut tls = TlsConfig::new();
+/// tls.set_server_name("one.one.one.one");
+/// let client = TlsClient::new("1.1.1.1:853".parse().unwrap(), tls, Duration::from_secs(5)).unwrap();
+///
+/// let mut message = Message::new();
+/// message.set_questions(vec!["example.com".to_string()]).unwrap();
+/// let response = client.send(&message).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct TlsClient {
+    server: SocketAddr,
+    name: ServerName<'static>,
+    config: Arc<rustls::ClientConfig>,
+    timeout: Duration,
+    stream: Mutex<Option<TlsStream>>,
+}
+impl TlsClient {
+    /// # Creates a new TlsClient
+    ///
+    /// # Errors
+    ///
+    /// returns [`DnsError::TlsError`] if a root certificate or the
+    /// authentication name is invalid.
+    pub fn new(server: SocketAddr, tls: TlsConfig, timeout: Duration) -> Result<TlsClient, DnsError> {
+        Ok(TlsClient {
+            server,
+            name: tls.name_for(server)?,
+            config: Arc::new(tls.client_config(b"dot")?),
+            timeout,
+            stream: Mutex::new(None),
+        })
+    }
+
+    /// # Returns the server
+    pub fn server(&self) -> SocketAddr {
+        self.server
+    }
+
+    /// # Sends the message and waits for the reply
+    pub fn send(&self, message: &Message) -> Result<Message, DnsError> {
+        self.exchange(message, self.timeout)
+    }
+
+    /// Sends the message, waiting up to `timeout`.
+    ///
+    /// A query on a kept open connection that fails is sent once more on a
+    /// new connection, the server may have closed the old one.
+    pub(crate) fn exchange(&self, message: &Message, timeout: Duration) -> Result<Message, DnsError> {
+        let data = message.encode()?;
+        let mut slot = self.stream.lock().unwrap();
+        if let Some(stream) = slot.as_mut() {
+            match self.query(stream, message, &data, timeout) {
+                Ok(response) => return Ok(response),
+                Err(DnsError::Timeout { .. }) => {
+                    *slot = None;
+                    return Err(DnsError::Timeout { server: self.server, timeout });
+                }
+                Err(_) => *slot = None,
+            }
+        }
+        let mut stream = self.connect(timeout)?;
+        let response = self.query(&mut stream, message, &data, timeout)?;
+        *slot = Some(stream);
+        Ok(response)
+    }
+
+    fn connect(&self, timeout: Duration) -> Result<TlsStream, DnsError> {
+        let tcp = TcpStream::connect_timeout(&self.server, timeout).map_err(|e| self.io_error(e, timeout))?;
+        tcp.set_nodelay(true).map_err(DnsError::TcpSocketError)?;
+        let connection = ClientConnection::new(self.config.clone(), self.name.clone()).map_err(tls_error)?;
+        Ok(StreamOwned::new(connection, tcp))
+    }
+
+    fn query(&self, stream: &mut TlsStream, query: &Message, data: &[u8], timeout: Duration) -> Result<Message, DnsError> {
+        let deadline = Instant::now() + timeout;
+        // the handshake of a new connection reads while the query is written
+        stream.sock.set_read_timeout(Some(timeout)).map_err(DnsError::TcpSocketError)?;
+        stream.sock.set_write_timeout(Some(timeout)).map_err(DnsError::TcpSocketError)?;
+        write_frame(stream, data).map_err(|e| self.io_error(e, timeout))?;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(DnsError::Timeout { server: self.server, timeout });
+            }
+            stream.sock.set_read_timeout(Some(remaining)).map_err(DnsError::TcpSocketError)?;
+            let buf = read_frame(stream).map_err(|e| self.io_error(e, timeout))?;
+            let response = Message::parse(&buf)?;
+            if response.is_response_to(query) {
+                return Ok(response);
+            }
+        }
+    }
+
+    /// Keeps the timeouts apart from the TLS errors, rustls reports
+    /// handshake failures as I/O errors.
+    fn io_error(&self, e: io::Error, timeout: Duration) -> DnsError {
+        match e.kind() {
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => DnsError::Timeout { server: self.server, timeout },
+            _ => DnsError::TlsError(e),
+        }
+    }
+}
+
+/// Sends queries over DNS over TLS, one [`TlsClient`] per server, all
+/// authenticated as set by the [`TlsConfig`].
+#[derive(Debug)]
+pub struct TlsTransport {
+    tls: TlsConfig,
+    connections: Pool<TlsClient>,
+}
+impl TlsTransport {
+    /// # Creates a new TlsTransport
+    pub fn new(tls: TlsConfig) -> TlsTransport {
+        TlsTransport {
+            tls,
+            connections: Pool::new(),
+        }
+    }
+}
+impl Transport for TlsTransport {
+    fn send(&self, server: SocketAddr, query: &Message, timeout: Duration) -> Result<Message, DnsError> {
+        let connection = self.connections.get(server, || TlsClient::new(server, self.tls.clone(), timeout))?;
+        connection.exchange(query, timeout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Client, ClientConfig, Protocol, RData, Resource};
+    use rcgen::{BasicConstraints, CertificateParams, IsCa, KeyPair};
+    use rustls::{pki_types::PrivateKeyDer, ServerConnection};
+    use std::{net::TcpListener, thread};
+
+    /// A self-signed CA and a certificate for `dns.test` signed by it.
+    struct Pki {
+        ca: Vec<u8>,
+        cert: Vec<u8>,
+        key: Vec<u8>,
+    }
+
+    fn pki() -> Pki {
+        let ca_key = KeyPair::generate().unwrap();
+        let mut ca_params = CertificateParams::new(vec![]).unwrap();
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca = ca_params.self_signed(&ca_key).unwrap();
+        let key = KeyPair::generate().unwrap();
+        let cert = CertificateParams::new(vec!["dns.test".to_string()])
+            .unwrap()
+            .signed_by(&key, &ca, &ca_key)
+            .unwrap();
+        Pki {
+            ca: ca.der().to_vec(),
+            cert: cert.der().to_vec(),
+            key: key.serialize_der(),
+        }
+    }
+
+    /// Answers the queries on one TLS connection with an A record.
+    fn tls_server(pki: &Pki) -> (SocketAddr, thread::JoinHandle<usize>) {
+        tls_server_with_chain(vec![pki.cert.clone()], &pki.key)
+    }
+
+    /// Like [`tls_server`], presenting the certificates of `chain`.
+    fn tls_server_with_chain(chain: Vec<Vec<u8>>, key: &[u8]) -> (SocketAddr, thread::JoinHandle<usize>) {
+        let config = rustls::ServerConfig::builder_with_provider(Arc::new(default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(
+                chain.into_iter().map(CertificateDer::from).collect(),
+                PrivateKeyDer::Pkcs8(key.to_vec().into()),
+            )
+            .unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let (tcp, _) = listener.accept().unwrap();
+            let connection = ServerConnection::new(Arc::new(config)).unwrap();
+            let mut stream = StreamOwned::new(connection, tcp);
+            let mut answered = 0;
+            while let Ok(buf) = read_frame(&mut stream) {
+                let mut response = Message::parse(&buf).unwrap();
+                response.header.set_qr(true);
+                let name = response.question[0].qname.clone();
+                response.answer.push(Resource::new(name, 60, RData::A([192, 0, 2, 1].into())).unwrap());
+                write_frame(&mut stream, &response.encode().unwrap()).unwrap();
+                answered += 1;
+            }
+            answered
+        });
+        (addr, handle)
+    }
+
+    fn query(name: &str) -> Message {
+        let mut message = Message::new();
+        message.set_questions(vec![name.to_string()]).unwrap();
+        message
+    }
+
+    #[test]
+    fn tls_client_authenticates_the_server_name() {
+        let pki = pki();
+        let (addr, handle) = tls_server(&pki);
+        let mut tls = TlsConfig::new();
+        tls.clear_root_certificates();
+        tls.add_root_certificate(&pki.ca);
+        tls.set_server_name("dns.test");
+
+        let client = TlsClient::new(addr, tls, Duration::from_secs(5)).unwrap();
+        assert_eq!(client.send(&query("a.example")).unwrap().answer.len(), 1);
+        assert_eq!(client.send(&query("b.example")).unwrap().answer.len(), 1);
+        drop(client);
+        // both queries went over one connection
+        assert_eq!(handle.join().unwrap(), 2);
+    }
+
+    #[test]
+    fn client_sends_over_tls() {
+        let pki = pki();
+        let (addr, handle) = tls_server(&pki);
+        let mut tls = TlsConfig::new();
+        tls.add_root_certificate(&pki.ca);
+        tls.set_server_name("dns.test");
+        let mut config = ClientConfig::new();
+        config.set_servers(vec![addr]);
+        config.set_protocol(Protocol::Tls);
+        config.set_tls(tls);
+
+        let client = Client::new(config);
+        assert_eq!(client.send(&query("a.example")).unwrap().answer.len(), 1);
+        drop(client);
+        assert_eq!(handle.join().unwrap(), 1);
+    }
+
+    #[test]
+    fn tls_client_rejects_a_wrong_name() {
+        let pki = pki();
+        let (addr, handle) = tls_server(&pki);
+        let mut tls = TlsConfig::new();
+        tls.add_root_certificate(&pki.ca);
+        tls.set_server_name("other.test");
+
+        let client = TlsClient::new(addr, tls, Duration::from_secs(5)).unwrap();
+        assert!(matches!(client.send(&query("a.example")), Err(DnsError::TlsError(_))));
+        drop(client);
+        assert_eq!(handle.join().unwrap(), 0);
+    }
+
+    #[test]
+    fn tls_client_checks_spki_pins() {
+        let pki = pki();
+        let (addr, handle) = tls_server(&pki);
+        // no roots at all, the pin alone authenticates the server
+        let mut tls = TlsConfig::new();
+        tls.clear_root_certificates();
+        tls.add_spki_pin(TlsConfig::spki_pin(&pki.cert).unwrap());
+        let client = TlsClient::new(addr, tls, Duration::from_secs(5)).unwrap();
+        assert!(client.send(&query("a.example")).is_ok());
+        drop(client);
+        handle.join().unwrap();
+
+        let (addr, handle) = tls_server(&pki);
+        let mut tls = TlsConfig::new();
+        tls.add_root_certificate(&pki.ca);
+        tls.set_server_name("dns.test");
+        tls.add_spki_pin([0; 32]);
+        let client = TlsClient::new(addr, tls, Duration::from_secs(5)).unwrap();
+        assert!(matches!(client.send(&query("a.example")), Err(DnsError::TlsError(_))));
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn tls_client_ignores_pins_of_ca_certificates() {
+        let attacker = pki();
+        let pinned = pki();
+        // the attacker sends the pinned CA certificate after their own
+        let (addr, handle) = tls_server_with_chain(vec![attacker.cert.clone(), pinned.ca.clone()], &attacker.key);
+        let mut tls = TlsConfig::new();
+        tls.clear_root_certificates();
+        tls.add_spki_pin(TlsConfig::spki_pin(&pinned.ca).unwrap());
+        let client = TlsClient::new(addr, tls, Duration::from_secs(5)).unwrap();
+        assert!(matches!(client.send(&query("a.example")), Err(DnsError::TlsError(_))));
+        drop(client);
+        assert_eq!(handle.join().unwrap(), 0);
+    }
+
+    #[test]
+    fn tls_client_times_out_during_the_handshake() {
+        // accepts the connection, but never sends a ServerHello
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let (tcp, _) = listener.accept().unwrap();
+            thread::sleep(Duration::from_secs(2));
+            drop(tcp);
+        });
+        let client = TlsClient::new(addr, TlsConfig::new(), Duration::from_millis(200)).unwrap();
+        let start = Instant::now();
+        assert!(matches!(client.send(&query("a.example")), Err(DnsError::Timeout { .. })));
+        assert!(start.elapsed() < Duration::from_secs(2));
+        handle.join().unwrap();
+    }
+}
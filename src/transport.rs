@@ -0,0 +1,287 @@
+use std::{
+    collections::HashMap,
+    fmt, io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::{dns_error::DnsError, ClientConfig, Message, Protocol, TcpClient};
+
+/// Carries a query to a server and its reply back.
+///
+/// A [`crate::Client`] takes care of picking the servers, retransmission
+/// and failover, a transport only makes one attempt with one server.  The
+/// built-in transports are chosen by [`ClientConfig::set_protocol()`],
+/// others can be handed to [`crate::Client::with_transport()`], like a mock
+/// in tests.
+///
+/// # Example
+///
+/// ```
+/// use std::{net::SocketAddr, time::Duration};
+/// use dns::{dns_error::DnsError, Client, ClientConfig, Message, Transport};
+///
+/// /// Answers every query with an empty response.
+/// struct Mock;
+/// impl Transport for Mock {
+///     fn send(&self, _server: SocketAddr, query: &Message, _timeout: Duration) -> Result<Message, DnsError> {
+///         let mut response = query.clone();
+///         response.header.set_qr(true);
+///         Ok(response)
+///     }
+/// }
+///
+/// let client = Client::with_transport(ClientConfig::new(), Mock);
+/// assert!(client.send(&Message::new()).unwrap().header.qr());
+/// ```
+pub trait Transport: Send + Sync {
+    /// # Sends the query to the server and waits up to `timeout` for the reply
+    ///
+    /// # Errors
+    ///
+    /// returns [`DnsError::Timeout`] if the server did not answer in time,
+    /// which makes the client try the next server without giving up.
+    fn send(&self, server: SocketAddr, query: &Message, timeout: Duration) -> Result<Message, DnsError>;
+}
+impl<T: Transport + ?Sized> Transport for Arc<T> {
+    fn send(&self, server: SocketAddr, query: &Message, timeout: Duration) -> Result<Message, DnsError> {
+        (**self).send(server, query, timeout)
+    }
+}
+impl<T: Transport + ?Sized> Transport for Box<T> {
+    fn send(&self, server: SocketAddr, query: &Message, timeout: Duration) -> Result<Message, DnsError> {
+        (**self).send(server, query, timeout)
+    }
+}
+
+/// Returns the built-in transport for the protocol of `config`.
+pub(crate) fn for_config(config: &ClientConfig) -> Box<dyn Transport> {
+    match config.protocol() {
+        Protocol::Udp => Box::new(UdpTransport::new()),
+        Protocol::Tcp => Box::new(TcpTransport::new()),
+        #[cfg(feature = "tls")]
+        Protocol::Tls => Box::new(crate::TlsTransport::new(config.tls().clone())),
+        #[cfg(feature = "https")]
+        Protocol::Https => Box::new(crate::HttpsTransport::new(config.doh().clone())),
+        #[cfg(feature = "quic")]
+        Protocol::Quic => Box::new(crate::QuicTransport::new(config.tls().clone())),
+    }
+}
+
+/// How many clients a [`Pool`] keeps.
+const MAX_POOLED: usize = 64;
+/// How long a [`Pool`] keeps a client that is not used.
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The clients of a transport, one per server, created on first use.
+///
+/// Clients that were not used for a minute are dropped, and the ones used
+/// least recently when there are more than 64, so a transport that talks
+/// to many servers, like the one of an [`crate::IterativeResolver`], does
+/// not keep a connection to each of them for good.
+pub(crate) struct Pool<T> {
+    clients: Mutex<HashMap<SocketAddr, Pooled<T>>>,
+}
+struct Pooled<T> {
+    client: Arc<T>,
+    used: Instant,
+}
+impl<T> Pool<T> {
+    pub(crate) fn new() -> Pool<T> {
+        Pool {
+            clients: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the client for `server`, creating it with `create`.
+    ///
+    /// The client is created without holding the lock of the pool, which
+    /// would hold up the queries to all other servers.  If two threads
+    /// create one for the same server at once, the first one is kept.
+    pub(crate) fn get<F>(&self, server: SocketAddr, create: F) -> Result<Arc<T>, DnsError>
+    where
+        F: FnOnce() -> Result<T, DnsError>,
+    {
+        let mut clients = self.clients.lock().unwrap();
+        let now = Instant::now();
+        let expired = expire(&mut clients, now);
+        let found = clients.get_mut(&server).map(|pooled| {
+            pooled.used = now;
+            pooled.client.clone()
+        });
+        drop(clients);
+        // closing connections can take a moment as well
+        drop(expired);
+        if let Some(client) = found {
+            return Ok(client);
+        }
+
+        let client = Arc::new(create()?);
+        let mut clients = self.clients.lock().unwrap();
+        let pooled = clients.entry(server).or_insert(Pooled {
+            client,
+            used: Instant::now(),
+        });
+        let client = pooled.client.clone();
+        let evicted = expire(&mut clients, Instant::now());
+        drop(clients);
+        drop(evicted);
+        Ok(client)
+    }
+}
+
+/// Removes the clients idle for too long and the least recently used ones
+/// above the limit, returning them to be dropped without the lock.
+fn expire<T>(clients: &mut HashMap<SocketAddr, Pooled<T>>, now: Instant) -> Vec<Pooled<T>> {
+    let mut removed = vec![];
+    let idle: Vec<SocketAddr> = clients
+        .iter()
+        .filter(|(_, pooled)| now.duration_since(pooled.used) > POOL_IDLE_TIMEOUT)
+        .map(|(server, _)| *server)
+        .collect();
+    removed.extend(idle.iter().filter_map(|server| clients.remove(server)));
+    while clients.len() > MAX_POOLED {
+        let Some(oldest) = clients.iter().min_by_key(|(_, pooled)| pooled.used).map(|(server, _)| *server) else { break };
+        removed.extend(clients.remove(&oldest));
+    }
+    removed
+}
+impl<T> fmt::Debug for Pool<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let servers: Vec<SocketAddr> = self.clients.lock().unwrap().keys().copied().collect();
+        f.debug_struct("Pool").field("servers", &servers).finish()
+    }
+}
+
+/// Sends queries over UDP and repeats them over TCP if the reply is
+/// truncated.
+///
+/// Every query is sent from a new socket, with room for a reply as large as
+/// the EDNS(0) UDP payload size of the query.
+#[derive(Debug)]
+pub struct UdpTransport {
+    tcp: TcpTransport,
+}
+impl UdpTransport {
+    /// # Creates a new UdpTransport
+    pub fn new() -> UdpTransport {
+        UdpTransport { tcp: TcpTransport::new() }
+    }
+}
+impl Default for UdpTransport {
+    fn default() -> Self {
+        UdpTransport::new()
+    }
+}
+impl Transport for UdpTransport {
+    fn send(&self, server: SocketAddr, query: &Message, timeout: Duration) -> Result<Message, DnsError> {
+        let data = query.encode()?;
+        let size = match query.edns()? {
+            Some(edns) => edns.udp_payload_size.max(512),
+            None => 512,
+        };
+        let response = send_udp(query, &data, server, size as usize, timeout)?;
+        if response.header.tc() {
+            return self.tcp.send(server, query, timeout);
+        }
+        Ok(response)
+    }
+}
+
+/// Sends one datagram to `server` and waits up to `timeout` for the reply.
+///
+/// Datagrams from other addresses, that do not parse or that do not answer
+/// `query` are dropped, so an off-path attacker has to guess the ID and
+/// source port to get a forged answer accepted.
+fn send_udp(
+    query: &Message,
+    data: &[u8],
+    server: SocketAddr,
+    size: usize,
+    timeout: Duration,
+) -> Result<Message, DnsError> {
+    let local = match server {
+        SocketAddr::V4(_) => SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0),
+        SocketAddr::V6(_) => SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0),
+    };
+    let socket = UdpSocket::bind(local).map_err(DnsError::UdpSocketError)?;
+    socket.send_to(data, server).map_err(DnsError::UdpSocketError)?;
+
+    let deadline = Instant::now() + timeout;
+    let mut buf = vec![0; size];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(DnsError::Timeout { server, timeout });
+        }
+        socket.set_read_timeout(Some(remaining)).map_err(DnsError::UdpSocketError)?;
+        let (amt, from) = socket.recv_from(&mut buf).map_err(|e| match e.kind() {
+            // the kind depends on the platform
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => DnsError::Timeout { server, timeout },
+            _ => DnsError::UdpSocketError(e),
+        })?;
+        if from != server {
+            continue;
+        }
+        match Message::parse(&buf[..amt]) {
+            Ok(response) if response.is_response_to(query) => return Ok(response),
+            _ => continue,
+        }
+    }
+}
+
+/// Sends queries over TCP on connections that are kept open between
+/// queries, one [`TcpClient`] per server.
+#[derive(Debug)]
+pub struct TcpTransport {
+    connections: Pool<TcpClient>,
+}
+impl TcpTransport {
+    /// # Creates a new TcpTransport
+    pub fn new() -> TcpTransport {
+        TcpTransport { connections: Pool::new() }
+    }
+}
+impl Default for TcpTransport {
+    fn default() -> Self {
+        TcpTransport::new()
+    }
+}
+impl Transport for TcpTransport {
+    fn send(&self, server: SocketAddr, query: &Message, timeout: Duration) -> Result<Message, DnsError> {
+        let connection = self.connections.get(server, || Ok(TcpClient::new(server, timeout)))?;
+        connection.exchange(std::slice::from_ref(query), timeout)?.remove(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn udp_transport_times_out() {
+        let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let result = UdpTransport::new().send(silent.local_addr().unwrap(), &Message::new(), Duration::from_millis(10));
+        assert!(matches!(result, Err(DnsError::Timeout { .. })));
+    }
+
+    #[test]
+    fn pool_expires_and_bounds_its_clients() {
+        let pool = Pool::new();
+        let server = |port| SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port);
+        let first = pool.get(server(1), || Ok(1)).unwrap();
+        assert!(Arc::ptr_eq(&first, &pool.get(server(1), || Ok(2)).unwrap()));
+
+        for port in 2..=MAX_POOLED as u16 + 1 {
+            pool.get(server(port), || Ok(port)).unwrap();
+        }
+        // the first server was used least recently
+        assert_eq!(pool.clients.lock().unwrap().len(), MAX_POOLED);
+        assert_eq!(*pool.get(server(1), || Ok(3)).unwrap(), 3);
+
+        let later = Instant::now() + POOL_IDLE_TIMEOUT + Duration::from_secs(1);
+        assert_eq!(expire(&mut pool.clients.lock().unwrap(), later).len(), MAX_POOLED);
+        assert!(pool.clients.lock().unwrap().is_empty());
+    }
+}
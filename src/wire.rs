@@ -0,0 +1,495 @@
+use std::{collections::HashMap, fmt, sync::Arc};
+
+use crate::{dns_error::DnsError, Name, RecordType, Section};
+
+/// The maximum length of a domain name in octets, including the length octets.
+pub(crate) const MAX_NAME_LEN: usize = 255;
+/// The largest offset a compression pointer can hold.
+const MAX_POINTER: usize = 0b0011_1111_1111_1111;
+
+/// A bounds-checked cursor over a received message.
+///
+/// Every read checks the remaining length first, so a truncated or hostile
+/// message turns into a [`DnsError`] carrying the offset and section instead
+/// of a panic.
+pub(crate) struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    section: Section,
+}
+impl<'a> Reader<'a> {
+    pub(crate) fn new(buf: &'a [u8]) -> Reader<'a> {
+        Reader {
+            buf,
+            pos: 0,
+            section: Section::Header,
+        }
+    }
+
+    pub(crate) fn set_section(&mut self, section: Section) {
+        self.section = section;
+    }
+
+    pub(crate) fn truncated(&self, offset: usize) -> DnsError {
+        DnsError::Truncated {
+            offset,
+            section: self.section,
+        }
+    }
+
+    pub(crate) fn bytes(&mut self, len: usize) -> Result<&'a [u8], DnsError> {
+        let end = self.pos.checked_add(len).filter(|end| *end <= self.buf.len());
+        match end {
+            Some(end) => {
+                let res = &self.buf[self.pos..end];
+                self.pos = end;
+                Ok(res)
+            }
+            None => Err(self.truncated(self.buf.len())),
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.pos == self.buf.len()
+    }
+
+    /// Returns the bytes from `start` to the position as they were received.
+    pub(crate) fn received(&self, start: usize) -> Received {
+        Received(Some((start, self.buf[start..self.pos].into())))
+    }
+
+    pub(crate) fn pos(&self) -> usize {
+        self.pos
+    }
+
+    /// Returns everything that was not read yet.
+    pub(crate) fn rest(&mut self) -> &'a [u8] {
+        let rest = &self.buf[self.pos..];
+        self.pos = self.buf.len();
+        rest
+    }
+
+    /// Reads a length octet followed by that number of octets.
+    pub(crate) fn character_string(&mut self) -> Result<&'a [u8], DnsError> {
+        let len = self.u8()?;
+        self.bytes(len as usize)
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8, DnsError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub(crate) fn u16(&mut self) -> Result<u16, DnsError> {
+        let b = self.bytes(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32, DnsError> {
+        let b = self.bytes(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    /// # Reads a domain name
+    ///
+    /// # Returns
+    ///
+    /// returns the decompressed name.
+    ///
+    /// # Note
+    ///
+    /// Compression pointers are followed wherever they appear in the name,
+    /// including chains of pointers.  Every pointer has to point strictly
+    /// before the start of the labels that were read before it, which rules
+    /// out both forward pointers and loops.  The decoded name may not be
+    /// longer than 255 octets.
+    pub(crate) fn name(&mut self) -> Result<Name, DnsError> {
+        let start = self.pos;
+        let mut name = vec![];
+        // position to continue at once the first pointer was followed
+        let mut resume = None;
+        // every pointer has to point before this offset
+        let mut limit = start;
+        let mut i = start;
+        loop {
+            let len = *self.buf.get(i).ok_or_else(|| self.truncated(i))?;
+            match len & 0b1100_0000 {
+                0b0000_0000 => {
+                    let end = i + 1 + len as usize;
+                    let label = self.buf.get(i..end).ok_or_else(|| self.truncated(self.buf.len()))?;
+                    if name.len() + label.len() > MAX_NAME_LEN {
+                        return Err(DnsError::NameTooLong {
+                            offset: start,
+                            section: self.section,
+                        });
+                    }
+                    name.extend_from_slice(label);
+                    i = end;
+                    if len == 0 {
+                        break;
+                    }
+                }
+                0b1100_0000 => {
+                    let low = *self.buf.get(i + 1).ok_or_else(|| self.truncated(i + 1))?;
+                    let target = u16::from_be_bytes([len & 0b0011_1111, low]) as usize;
+                    if target >= i {
+                        return Err(DnsError::InvalidPointer {
+                            offset: i,
+                            section: self.section,
+                        });
+                    }
+                    if target >= limit {
+                        return Err(DnsError::PointerLoop {
+                            offset: i,
+                            section: self.section,
+                        });
+                    }
+                    resume.get_or_insert(i + 2);
+                    limit = target;
+                    i = target;
+                }
+                _ => {
+                    return Err(DnsError::InvalidLabelType {
+                        offset: i,
+                        section: self.section,
+                    })
+                }
+            }
+        }
+        self.pos = resume.unwrap_or(i);
+        Ok(Name::from_wire_unchecked(name))
+    }
+
+    /// # Reads the RDATA of a resource record
+    ///
+    /// # Arguments
+    ///
+    /// takes the record type and the RDATA length.
+    ///
+    /// # Returns
+    ///
+    /// returns the RDATA with every embedded domain name decompressed, so
+    /// the bytes stay meaningful outside of this message.  Types without
+    /// embedded names are returned as they are.
+    pub(crate) fn rdata(&mut self, rtype: RecordType, rdlength: u16) -> Result<Vec<u8>, DnsError> {
+        let start = self.pos;
+        let end = start + rdlength as usize;
+        if end > self.buf.len() {
+            return Err(self.truncated(self.buf.len()));
+        }
+        if rdlength == 0 {
+            return Ok(vec![]);
+        }
+        let mut rdata = vec![];
+        match rtype {
+            RecordType::NS
+            | RecordType::MD
+            | RecordType::MF
+            | RecordType::CNAME
+            | RecordType::MB
+            | RecordType::MG
+            | RecordType::MR
+            | RecordType::PTR
+            | RecordType::DNAME => rdata.extend_from_slice(self.name()?.as_wire()),
+            RecordType::SOA => {
+                rdata.extend_from_slice(self.name()?.as_wire());
+                rdata.extend_from_slice(self.name()?.as_wire());
+                rdata.extend_from_slice(self.bytes(20)?);
+            }
+            RecordType::MINFO | RecordType::RP => {
+                rdata.extend_from_slice(self.name()?.as_wire());
+                rdata.extend_from_slice(self.name()?.as_wire());
+            }
+            RecordType::MX | RecordType::AFSDB | RecordType::RT | RecordType::KX => {
+                rdata.extend_from_slice(self.bytes(2)?);
+                rdata.extend_from_slice(self.name()?.as_wire());
+            }
+            RecordType::PX => {
+                rdata.extend_from_slice(self.bytes(2)?);
+                rdata.extend_from_slice(self.name()?.as_wire());
+                rdata.extend_from_slice(self.name()?.as_wire());
+            }
+            RecordType::SRV => {
+                rdata.extend_from_slice(self.bytes(6)?);
+                rdata.extend_from_slice(self.name()?.as_wire());
+            }
+            RecordType::NAPTR => {
+                rdata.extend_from_slice(self.bytes(4)?);
+                for _ in 0..3 {
+                    let len = self.u8()?;
+                    rdata.push(len);
+                    rdata.extend_from_slice(self.bytes(len as usize)?);
+                }
+                rdata.extend_from_slice(self.name()?.as_wire());
+            }
+            _ => return Ok(self.bytes(rdlength as usize)?.to_vec()),
+        }
+        if self.pos != end {
+            return Err(DnsError::InvalidRdata {
+                offset: start,
+                section: self.section,
+            });
+        }
+        Ok(rdata)
+    }
+}
+
+/// The bytes of a question or record as they were received and the offset
+/// they were found at, with the names compressed as the sender did it.
+///
+/// They are not part of what the question or record means, so any two
+/// compare equal.
+#[derive(Clone, Default)]
+pub(crate) struct Received(Option<(usize, Arc<[u8]>)>);
+impl PartialEq for Received {
+    fn eq(&self, _other: &Received) -> bool {
+        true
+    }
+}
+impl Eq for Received {}
+impl fmt::Debug for Received {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.0 {
+            Some((offset, bytes)) => write!(f, "Some({offset}..{})", offset + bytes.len()),
+            None => f.write_str("None"),
+        }
+    }
+}
+
+/// Builds the wire format of a message.
+///
+/// When compression is enabled every name suffix written is remembered in
+/// a per-message table, and later occurrences of the same suffix are
+/// replaced with a pointer to it (RFC 1035 section 4.1.4).
+pub(crate) struct Writer {
+    buf: Vec<u8>,
+    compress: bool,
+    /// offsets of the name suffixes written so far
+    names: HashMap<Vec<u8>, u16>,
+}
+impl Writer {
+    pub(crate) fn new(compress: bool) -> Writer {
+        Writer {
+            buf: vec![],
+            compress,
+            names: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn finish(self) -> Vec<u8> {
+        self.buf
+    }
+
+    pub(crate) fn bytes(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// # Writes bytes as they were received
+    ///
+    /// # Returns
+    ///
+    /// returns true if the bytes were written.  Without compression, bytes
+    /// that land at the offset they were received at are written as they
+    /// are, compression pointers included, if `check` reads back exactly
+    /// them from the message written so far and accepts what it read.
+    /// Otherwise nothing is written and the caller writes its own form.
+    pub(crate) fn received<F>(&mut self, received: &Received, check: F) -> bool
+    where
+        F: FnOnce(&mut Reader) -> bool,
+    {
+        let Some((offset, bytes)) = &received.0 else { return false };
+        if self.compress || *offset != self.buf.len() {
+            return false;
+        }
+        self.buf.extend_from_slice(bytes);
+        let mut reader = Reader::new(&self.buf);
+        reader.pos = *offset;
+        if check(&mut reader) && reader.is_empty() {
+            return true;
+        }
+        self.buf.truncate(*offset);
+        false
+    }
+
+    pub(crate) fn u16(&mut self, value: u16) {
+        self.bytes(&value.to_be_bytes());
+    }
+
+    pub(crate) fn u32(&mut self, value: u32) {
+        self.bytes(&value.to_be_bytes());
+    }
+
+    /// # Writes a domain name
+    ///
+    /// # Arguments
+    ///
+    /// takes the name as uncompressed label bytes.
+    pub(crate) fn name(&mut self, name: &[u8]) {
+        let mut i = 0;
+        while i < name.len() {
+            let suffix = &name[i..];
+            if suffix[0] == 0 {
+                self.buf.push(0);
+                return;
+            }
+            if self.compress {
+                if let Some(&offset) = self.names.get(suffix) {
+                    self.u16(0b1100_0000_0000_0000 | offset);
+                    return;
+                }
+                // pointers only have 14 bits for the offset
+                if self.buf.len() <= MAX_POINTER {
+                    self.names.insert(suffix.to_vec(), self.buf.len() as u16);
+                }
+            }
+            let end = (i + 1 + suffix[0] as usize).min(name.len());
+            self.bytes(&name[i..end]);
+            i = end;
+        }
+    }
+
+    /// # Writes RDLENGTH followed by the RDATA
+    ///
+    /// # Note
+    ///
+    /// Names inside the RDATA are only compressed for the types defined in
+    /// RFC 1035, as required by RFC 3597 section 4.
+    pub(crate) fn rdata(&mut self, rtype: RecordType, rdata: &[u8], section: Section) -> Result<(), DnsError> {
+        let len_pos = self.buf.len();
+        self.u16(0);
+        let start = self.buf.len();
+        match self.compress.then(|| rdata_pieces(rtype, rdata)).flatten() {
+            Some(pieces) => {
+                for piece in pieces {
+                    match piece {
+                        Piece::Name(name) => self.name(name),
+                        Piece::Raw(raw) => self.bytes(raw),
+                    }
+                }
+            }
+            None => self.bytes(rdata),
+        }
+        let len = u16::try_from(self.buf.len() - start).map_err(|_| DnsError::RdataTooLong {
+            offset: len_pos,
+            section,
+        })?;
+        self.buf[len_pos..start].copy_from_slice(&len.to_be_bytes());
+        Ok(())
+    }
+}
+
+/// Part of an RDATA, see [`rdata_pieces`].
+enum Piece<'a> {
+    Name(&'a [u8]),
+    Raw(&'a [u8]),
+}
+
+/// Splits the RDATA of the RFC 1035 types into the names that may be
+/// compressed and the bytes around them.  Returns `None` for other types
+/// or RDATA that does not have the expected layout.
+fn rdata_pieces(rtype: RecordType, rdata: &[u8]) -> Option<Vec<Piece<'_>>> {
+    let mut reader = Reader::new(rdata);
+    let name = |reader: &mut Reader<'_>| {
+        let start = reader.pos;
+        reader.name().ok()?;
+        Some(Piece::Name(&rdata[start..reader.pos]))
+    };
+    let pieces = match rtype {
+        RecordType::NS
+        | RecordType::MD
+        | RecordType::MF
+        | RecordType::CNAME
+        | RecordType::MB
+        | RecordType::MG
+        | RecordType::MR
+        | RecordType::PTR => vec![name(&mut reader)?],
+        RecordType::SOA => vec![
+            name(&mut reader)?,
+            name(&mut reader)?,
+            Piece::Raw(reader.bytes(20).ok()?),
+        ],
+        RecordType::MINFO => vec![name(&mut reader)?, name(&mut reader)?],
+        RecordType::MX => vec![Piece::Raw(reader.bytes(2).ok()?), name(&mut reader)?],
+        _ => return None,
+    };
+    (reader.pos == rdata.len()).then_some(pieces)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Header followed by `example.com` at offset 12 and the given bytes.
+    fn message(rest: &[u8]) -> Vec<u8> {
+        let mut buf = vec![0; 12];
+        buf.extend_from_slice(b"\x07example\x03com\x00");
+        buf.extend_from_slice(rest);
+        buf
+    }
+
+    #[test]
+    fn name_follows_chained_pointers() {
+        // offset 25: www -> example.com, offset 31: a -> www.example.com
+        let buf = message(&[3, b'w', b'w', b'w', 0xc0, 12, 1, b'a', 0xc0, 25]);
+        let mut reader = Reader::new(&buf);
+        reader.pos = 31;
+        assert_eq!(reader.name().unwrap().as_wire(), b"\x01a\x03www\x07example\x03com\x00");
+        assert_eq!(reader.pos, 35);
+    }
+
+    #[test]
+    fn name_rejects_forward_pointers_and_loops() {
+        let buf = message(&[0xc0, 27, 0xc0, 25]);
+        let mut reader = Reader::new(&buf);
+        reader.pos = 25;
+        assert!(matches!(reader.name(), Err(DnsError::InvalidPointer { offset: 25, .. })));
+
+        // the label at 25 swallows the pointer at 27 which jumps back to 25
+        let buf = message(&[2, 0xc0, 25, 0xc0, 25]);
+        let mut reader = Reader::new(&buf);
+        reader.pos = 28;
+        assert!(matches!(reader.name(), Err(DnsError::PointerLoop { offset: 28, .. })));
+    }
+
+    #[test]
+    fn name_rejects_more_than_255_octets() {
+        let mut rest = vec![];
+        for _ in 0..4 {
+            rest.push(63);
+            rest.extend_from_slice(&[b'a'; 63]);
+        }
+        rest.push(0);
+        let buf = message(&rest);
+        let mut reader = Reader::new(&buf);
+        reader.pos = 25;
+        assert!(matches!(reader.name(), Err(DnsError::NameTooLong { offset: 25, .. })));
+    }
+
+    #[test]
+    fn rdata_names_are_decompressed() {
+        // MX 10 mail.example.com
+        let buf = message(&[0, 10, 4, b'm', b'a', b'i', b'l', 0xc0, 12]);
+        let mut reader = Reader::new(&buf);
+        reader.pos = 25;
+        let rdata = reader.rdata(RecordType::MX, 9).unwrap();
+        assert_eq!(rdata, b"\x00\x0a\x04mail\x07example\x03com\x00");
+
+        let mut reader = Reader::new(&buf);
+        reader.pos = 25;
+        assert!(matches!(reader.rdata(RecordType::MX, 10), Err(DnsError::Truncated { .. })));
+        let mut reader = Reader::new(&buf);
+        reader.pos = 25;
+        assert!(matches!(reader.rdata(RecordType::MX, 8), Err(DnsError::InvalidRdata { offset: 25, .. })));
+    }
+
+    #[test]
+    fn writer_compresses_rfc1035_rdata_only() {
+        let mut writer = Writer::new(true);
+        writer.name(b"\x07example\x03com\x00");
+        writer.rdata(RecordType::MX, b"\x00\x0a\x04mail\x07example\x03com\x00", Section::Answer).unwrap();
+        // SRV targets must not be compressed
+        writer.rdata(RecordType::SRV, b"\x00\x00\x00\x00\x00\x35\x07example\x03com\x00", Section::Answer).unwrap();
+        let buf = writer.finish();
+        assert_eq!(&buf[13..24], b"\x00\x09\x00\x0a\x04mail\xc0\x00");
+        assert_eq!(&buf[24..26], &[0, 19]);
+    }
+}